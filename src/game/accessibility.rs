@@ -0,0 +1,189 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    layer::Layer,
+    level::{Level, SymbolType},
+    player::CommonDuck,
+    save::{changed_after_added, load_ron, save_ron},
+    *,
+};
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_ron::<Accessibility>(ACCESSIBILITY_NAME).unwrap_or_default())
+            .add_systems(
+                Update,
+                (
+                    write_accessibility.run_if(changed_after_added::<Accessibility>),
+                    (update_marks, follow_ducks)
+                        .chain()
+                        .run_if(in_state(GameStates::Next)),
+                ),
+            );
+    }
+}
+
+const ACCESSIBILITY_NAME: &str = "accessibility.ron";
+
+// The ui scale is multiplied by it with large text on
+pub const LARGE_TEXT_SCALE: f32 = 1.25;
+
+// Extra help for players who can't tell the sprites apart or don't like the wobbling
+#[derive(Resource, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct Accessibility {
+    // Marks on the tiles and bread counters on the ducks
+    pub symbols: bool,
+    pub large_text: bool,
+    // No squashing ducks
+    pub reduced_motion: bool,
+    // Moves told in words, see narration.rs
+    pub narration: bool,
+}
+
+// One switch of the settings panel
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AccessibilityOption {
+    Symbols,
+    LargeText,
+    ReducedMotion,
+    Narration,
+}
+
+impl AccessibilityOption {
+    pub const ALL: [AccessibilityOption; 4] = [
+        AccessibilityOption::Symbols,
+        AccessibilityOption::LargeText,
+        AccessibilityOption::ReducedMotion,
+        AccessibilityOption::Narration,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AccessibilityOption::Symbols => "Symbols",
+            AccessibilityOption::LargeText => "Large text",
+            AccessibilityOption::ReducedMotion => "Reduced motion",
+            AccessibilityOption::Narration => "Narration",
+        }
+    }
+
+    pub fn is_on(self, accessibility: &Accessibility) -> bool {
+        match self {
+            AccessibilityOption::Symbols => accessibility.symbols,
+            AccessibilityOption::LargeText => accessibility.large_text,
+            AccessibilityOption::ReducedMotion => accessibility.reduced_motion,
+            AccessibilityOption::Narration => accessibility.narration,
+        }
+    }
+
+    pub fn toggle(self, accessibility: &mut Accessibility) {
+        let value = match self {
+            AccessibilityOption::Symbols => &mut accessibility.symbols,
+            AccessibilityOption::LargeText => &mut accessibility.large_text,
+            AccessibilityOption::ReducedMotion => &mut accessibility.reduced_motion,
+            AccessibilityOption::Narration => &mut accessibility.narration,
+        };
+        *value = !*value;
+    }
+}
+
+// Text over a tile or beside a duck, respawned whenever the board changes
+#[derive(Component)]
+struct Mark;
+
+// Follows the duck, the children of a duck are cleared when it's no longer chosen
+#[derive(Component)]
+struct DuckCounter(Entity);
+
+// What the tile is, for the tiles told apart only by sprite detail
+fn tile_mark(symbol_type: SymbolType) -> Option<(&'static str, Color)> {
+    match symbol_type {
+        SymbolType::BreakingIce | SymbolType::DuckOnBreakingIce => Some(("X", Color::BLACK)),
+        SymbolType::BrokenIce | SymbolType::DuckOnWater => Some(("~", Color::WHITE)),
+        SymbolType::MoldyBreadOnIce => Some(("!", Color::BLACK)),
+        _ => None,
+    }
+}
+
+// Corner of the tile, ducks cover the middle
+const MARK_OFFSET: Vec3 = Vec3::new(-SPRITE_SIZE * 0.3, SPRITE_SIZE * 0.3, 0.0);
+const COUNTER_OFFSET: Vec3 = Vec3::new(SPRITE_SIZE * 0.3, -SPRITE_SIZE * 0.3, 0.0);
+
+fn update_marks(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    accessibility: Res<Accessibility>,
+    level: Res<Level>,
+    duck_query: Query<(Entity, &CommonDuck, &Transform)>,
+    changed_duck_query: Query<(), Changed<CommonDuck>>,
+    mark_query: Query<Entity, With<Mark>>,
+) {
+    if !accessibility.is_changed() && !level.is_changed() && changed_duck_query.is_empty() {
+        return;
+    }
+    for entity in mark_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    if !accessibility.symbols {
+        return;
+    }
+
+    let text_style = |color| TextStyle {
+        font: asset_server.load("fonts/NotJamChunky8.ttf"),
+        font_size: 16.0,
+        color,
+    };
+    for (row_index, row) in level.0.iter().enumerate() {
+        for (col_index, &ch) in row.iter().enumerate() {
+            let Some((mark, color)) = SymbolType::from_symbol(ch).and_then(tile_mark) else {
+                continue;
+            };
+            commands.spawn((
+                Text2dBundle {
+                    text: Text::from_section(mark, text_style(color)),
+                    transform: Transform::from_translation(
+                        Layer::Overlay.translation((row_index, col_index)) + MARK_OFFSET,
+                    ),
+                    ..default()
+                },
+                Mark,
+                Layer::Overlay,
+            ));
+        }
+    }
+    for (entity, duck, transform) in duck_query.iter() {
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    format!("{}/{}", duck.bread_sum, duck.belly_capacity),
+                    text_style(Color::BLACK),
+                ),
+                transform: Transform::from_translation(
+                    transform.translation.with_z(Layer::Overlay.z()) + COUNTER_OFFSET,
+                ),
+                ..default()
+            },
+            Mark,
+            Layer::Overlay,
+            DuckCounter(entity),
+        ));
+    }
+}
+
+fn follow_ducks(
+    mut counter_query: Query<(&mut Transform, &DuckCounter)>,
+    duck_query: Query<&Transform, (With<CommonDuck>, Without<DuckCounter>)>,
+) {
+    for (mut transform, DuckCounter(duck)) in counter_query.iter_mut() {
+        if let Ok(duck_transform) = duck_query.get(*duck) {
+            transform.translation =
+                duck_transform.translation.with_z(Layer::Overlay.z()) + COUNTER_OFFSET;
+        }
+    }
+}
+
+fn write_accessibility(accessibility: Res<Accessibility>) {
+    save_ron(ACCESSIBILITY_NAME, &*accessibility);
+}
@@ -0,0 +1,160 @@
+use bevy::utils::Duration;
+
+use super::{
+    accessibility::Accessibility,
+    player::{CommonDuck, Duck, Landing},
+    *,
+};
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, animate_ducks.run_if(in_state(GameStates::Next)));
+    }
+}
+
+// How long an idle frame shows
+const FRAME_SECS: f32 = 0.5;
+const EAT_MILI_SECS: u64 = 400;
+
+// What the duck is doing, frames of duck_atlas.png
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum DuckClip {
+    #[default]
+    Idle,
+    Slide,
+    Eat,
+    Sink,
+}
+
+impl DuckClip {
+    // The first row is the hungry duck, the second row the stuffed one
+    pub fn frames(self, duck: &CommonDuck) -> &'static [usize] {
+        let stuffed = duck.is_stuffed();
+        let sunk = stuffed && !duck.can_move();
+        match self {
+            DuckClip::Idle if sunk => &[7],
+            DuckClip::Idle if stuffed => &[4, 5],
+            DuckClip::Idle => &[0, 1],
+            DuckClip::Slide if stuffed => &[6],
+            DuckClip::Slide => &[2],
+            DuckClip::Eat => &[3],
+            DuckClip::Sink => &[7],
+        }
+    }
+
+    // None plays until something else does
+    fn duration(self) -> Option<Duration> {
+        match self {
+            DuckClip::Eat => Some(Duration::from_millis(EAT_MILI_SECS)),
+            _ => None,
+        }
+    }
+
+    // Played when the slide ends
+    pub fn from_landing(landing: Landing) -> Self {
+        match landing {
+            Landing::Stops => DuckClip::Idle,
+            Landing::EatsBread | Landing::EatsMoldyBread => DuckClip::Eat,
+            Landing::Sinks => DuckClip::Sink,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct DuckAnimation {
+    clip: DuckClip,
+    frame: usize,
+    frame_timer: Timer,
+    clip_timer: Option<Timer>,
+    // Played after the clip
+    then: DuckClip,
+}
+
+impl Default for DuckAnimation {
+    fn default() -> Self {
+        DuckAnimation {
+            clip: DuckClip::Idle,
+            frame: 0,
+            frame_timer: Timer::from_seconds(FRAME_SECS, TimerMode::Repeating),
+            clip_timer: None,
+            then: DuckClip::Idle,
+        }
+    }
+}
+
+impl DuckAnimation {
+    pub fn clip(&self) -> DuckClip {
+        self.clip
+    }
+
+    pub fn play(&mut self, clip: DuckClip, duration: Option<Duration>, then: DuckClip) {
+        self.clip = clip;
+        self.frame = 0;
+        self.frame_timer.reset();
+        self.clip_timer = duration.map(|duration| Timer::new(duration, TimerMode::Once));
+        self.then = then;
+    }
+}
+
+// The duck sprite in the atlas, for the ducks and their previews
+pub fn duck_atlas(image_assets: &ImageAssets, duck: &CommonDuck) -> TextureAtlas {
+    TextureAtlas {
+        layout: image_assets.duck_layout.clone(),
+        index: DuckClip::Idle.frames(duck)[0],
+    }
+}
+
+// Terrain in tile_atlas.png, each kind has a plain frame and a flipped one
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TileKind {
+    Ice,
+    Wall,
+    Water,
+    BreakingIce,
+}
+
+impl TileKind {
+    // A third of the tiles take the flipped frame, picked by position so it stays put between moves
+    pub fn atlas(self, image_assets: &ImageAssets, position: Vec3) -> TextureAtlas {
+        let col = (position.x / SPRITE_SIZE).round() as i64;
+        let row = (position.y / SPRITE_SIZE).round() as i64;
+        let variant = ((col * 7 + row * 13).rem_euclid(3) == 0) as usize;
+        TextureAtlas {
+            layout: image_assets.tile_layout.clone(),
+            index: self as usize * 2 + variant,
+        }
+    }
+}
+
+fn animate_ducks(
+    time: Res<Time>,
+    accessibility: Res<Accessibility>,
+    mut duck_query: Query<(&mut DuckAnimation, &mut TextureAtlas, &CommonDuck)>,
+) {
+    for (mut animation, mut atlas, duck) in duck_query.iter_mut() {
+        let clip_finished = animation.clip_timer.as_mut().is_some_and(|timer| {
+            timer.tick(time.delta());
+            timer.finished()
+        });
+        if clip_finished {
+            let then = animation.then;
+            animation.play(then, then.duration(), DuckClip::Idle);
+        }
+        if animation.frame_timer.tick(time.delta()).just_finished() {
+            animation.frame += 1;
+        }
+        // Idle ducks stand still with reduced motion
+        let frame = if accessibility.reduced_motion && animation.clip == DuckClip::Idle {
+            0
+        } else {
+            animation.frame
+        };
+        let frames = animation.clip.frames(duck);
+        let index = frames[frame % frames.len()];
+        if atlas.index != index {
+            atlas.index = index;
+        }
+    }
+}
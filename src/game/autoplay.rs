@@ -0,0 +1,201 @@
+use super::{
+    animation::{DuckAnimation, DuckClip},
+    controls::{Action, Actions},
+    level::{
+        CurrentLevelIndex, Level, LevelConfig, LevelStack, RestartLevel, TotalBreadCount, UndoLevel,
+    },
+    player::{CommonDuck, MoveDuck},
+    replay::Step,
+    solver::{SolveTask, Verdict, SOLUTION_SOLVER_BUDGET},
+    *,
+};
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Autoplay>().add_systems(
+            Update,
+            (autoplay_controls, play_moves.after(autoplay_controls))
+                .run_if(in_state(GameStates::Next)),
+        );
+    }
+}
+
+// Seconds between the end of a slide and the next move at speed x1
+const MOVE_INTERVAL_SECS: f32 = 0.5;
+const MIN_SPEED: f32 = 0.25;
+const MAX_SPEED: f32 = 4.0;
+
+// Plays a list of steps through the same events the keyboard sends
+#[derive(Resource)]
+pub struct Autoplay {
+    pub steps: Vec<Step>,
+    // Index of the next step to play
+    pub next: usize,
+    pub paused: bool,
+    pub speed: f32,
+    timer: Timer,
+    // Undo and restart sent by the autoplay itself, only the player's stop it
+    sent_level_events: usize,
+}
+
+impl Default for Autoplay {
+    fn default() -> Self {
+        Self {
+            steps: Vec::new(),
+            next: 0,
+            paused: false,
+            speed: 1.0,
+            timer: Timer::from_seconds(MOVE_INTERVAL_SECS, TimerMode::Repeating),
+            sent_level_events: 0,
+        }
+    }
+}
+
+impl Autoplay {
+    pub fn is_active(&self) -> bool {
+        self.next < self.steps.len()
+    }
+
+    pub fn start(&mut self, steps: Vec<Step>) {
+        self.steps = steps;
+        self.next = 0;
+        self.paused = false;
+        self.timer.reset();
+    }
+
+    pub fn stop(&mut self) {
+        self.steps.clear();
+        self.next = 0;
+    }
+}
+
+// Play the solution from the current board or stop, pause, step while paused, slower, faster
+// The solution is worked out in the background, pressing again while it is drops it
+pub fn autoplay_controls(
+    actions: Res<Actions>,
+    level: Res<Level>,
+    config: Res<LevelConfig>,
+    level_stack: Res<LevelStack>,
+    total_bread_count: Res<TotalBreadCount>,
+    level_index: Res<CurrentLevelIndex>,
+    mut autoplay: ResMut<Autoplay>,
+    mut events_restart: EventReader<RestartLevel>,
+    mut events_undo: EventReader<UndoLevel>,
+    mut solving: Local<Option<SolveTask>>,
+) {
+    // The steps don't fit the board anymore
+    let level_events = events_restart.read().count() + events_undo.read().count();
+    let sent_level_events = autoplay.sent_level_events;
+    if sent_level_events > 0 {
+        autoplay.sent_level_events = 0;
+    }
+    let restarted_or_undone = level_events > sent_level_events;
+    if autoplay.is_active() && (level_index.is_changed() || restarted_or_undone) {
+        autoplay.stop();
+        return;
+    }
+    // The board the solve was started for is gone
+    if level.is_changed() || level_stack.is_changed() {
+        *solving = None;
+    }
+    if actions.just_pressed(Action::Autoplay) {
+        if autoplay.is_active() {
+            autoplay.stop();
+        } else if solving.is_some() {
+            *solving = None;
+        } else {
+            *solving = Some(SolveTask::spawn(
+                &level.0,
+                &config,
+                level_stack.0.size().saturating_sub(1),
+                total_bread_count.0,
+                SOLUTION_SOLVER_BUDGET,
+            ));
+        }
+    }
+    if let Some(verdict) = solving.as_mut().and_then(SolveTask::poll) {
+        *solving = None;
+        match verdict {
+            Verdict::Solvable(moves) => {
+                autoplay.start(moves.into_iter().map(Step::Move).collect());
+            }
+            Verdict::Unsolvable => info!("Nothing to play, the level can't be won from here"),
+            Verdict::Unknown => info!("Nothing to play, the solver gave up"),
+        }
+    }
+    if !autoplay.is_active() {
+        return;
+    }
+    if actions.just_pressed(Action::PauseAutoplay) {
+        autoplay.paused = !autoplay.paused;
+    }
+    if actions.just_pressed(Action::SlowerAutoplay) {
+        autoplay.speed = (autoplay.speed / 2.0).max(MIN_SPEED);
+    }
+    if actions.just_pressed(Action::FasterAutoplay) {
+        autoplay.speed = (autoplay.speed * 2.0).min(MAX_SPEED);
+    }
+}
+
+// Each step waits for the slide before it to end, however fast the autoplay goes
+fn play_moves(
+    time: Res<Time>,
+    actions: Res<Actions>,
+    duck_query: Query<(Entity, &CommonDuck)>,
+    animation_query: Query<&DuckAnimation>,
+    mut autoplay: ResMut<Autoplay>,
+    mut events_move: EventWriter<MoveDuck>,
+    mut events_undo: EventWriter<UndoLevel>,
+    mut events_restart: EventWriter<RestartLevel>,
+) {
+    if !autoplay.is_active() {
+        return;
+    }
+    if animation_query
+        .iter()
+        .any(|animation| animation.clip() == DuckClip::Slide)
+    {
+        return;
+    }
+    let should_move = if autoplay.paused {
+        actions.just_pressed(Action::StepAutoplay)
+    } else {
+        let delta = time.delta().mul_f32(autoplay.speed);
+        autoplay.timer.tick(delta).just_finished()
+    };
+    if !should_move {
+        return;
+    }
+
+    let duck_move = match autoplay.steps[autoplay.next] {
+        Step::Move(duck_move) => duck_move,
+        Step::Undo => {
+            events_undo.send(UndoLevel);
+            autoplay.sent_level_events += 1;
+            autoplay.next += 1;
+            return;
+        }
+        Step::Restart => {
+            events_restart.send(RestartLevel);
+            autoplay.sent_level_events += 1;
+            autoplay.next += 1;
+            return;
+        }
+    };
+    // The steps were made for the board they are played on, so the position picks the duck
+    let Some((entity, _)) = duck_query
+        .iter()
+        .find(|(_, duck)| duck.logic_position == duck_move.position)
+    else {
+        warn!("Autoplay stopped, no duck at {:?}", duck_move.position);
+        autoplay.stop();
+        return;
+    };
+    events_move.send(MoveDuck {
+        duck: entity,
+        direction: duck_move.direction,
+    });
+    autoplay.next += 1;
+}
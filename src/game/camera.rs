@@ -0,0 +1,62 @@
+use super::{level::Level, *};
+use bevy::render::camera::ScalingMode;
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_camera)
+            .add_systems(Update, fit_camera.run_if(resource_changed::<Level>));
+    }
+}
+
+// Room around the board for the texts on the sides
+const BOARD_MARGIN: f32 = 2.0 * SPRITE_SIZE;
+// The view the game was drawn for, small boards aren't zoomed in past it
+const MIN_VIEW_WIDTH: f32 = 1280.0;
+const MIN_VIEW_HEIGHT: f32 = 720.0;
+
+fn spawn_camera(mut commands: Commands) {
+    let mut my_2d_camera_bundle = Camera2dBundle::default();
+    my_2d_camera_bundle.projection.scaling_mode = ScalingMode::AutoMin {
+        min_width: MIN_VIEW_WIDTH,
+        min_height: MIN_VIEW_HEIGHT,
+    };
+    commands.spawn(my_2d_camera_bundle);
+}
+
+// Center the board and make it fit with its margins
+// AutoMin keeps it fitting when the window is resized
+fn fit_camera(
+    level: Res<Level>,
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection), With<Camera2d>>,
+) {
+    let rows = level.0.len();
+    let cols = level.0.iter().map(Vec::len).max().unwrap_or(0);
+    if rows == 0 || cols == 0 {
+        return;
+    }
+    let center = (logic_position_to_translation((0, 0))
+        + logic_position_to_translation((rows - 1, cols - 1)))
+        / 2.0;
+    let min_width = (cols as f32 * SPRITE_SIZE + 2.0 * BOARD_MARGIN).max(MIN_VIEW_WIDTH);
+    let min_height = (rows as f32 * SPRITE_SIZE + 2.0 * BOARD_MARGIN).max(MIN_VIEW_HEIGHT);
+    for (mut transform, mut projection) in camera_query.iter_mut() {
+        transform.translation.x = center.x;
+        transform.translation.y = center.y;
+        if let ScalingMode::AutoMin {
+            min_width: old_width,
+            min_height: old_height,
+        } = projection.scaling_mode
+        {
+            // Moves change the level too, the size only changes with the board
+            if old_width == min_width && old_height == min_height {
+                continue;
+            }
+        }
+        projection.scaling_mode = ScalingMode::AutoMin {
+            min_width,
+            min_height,
+        };
+    }
+}
@@ -0,0 +1,510 @@
+use std::collections::BTreeMap;
+
+use bevy::utils::HashSet;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    save::{changed_after_added, load_ron, save_ron},
+    *,
+};
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Bindings::load())
+            .init_resource::<Actions>()
+            .init_resource::<ControlsMenu>()
+            .add_systems(PreUpdate, read_actions.after(bevy::input::InputSystem))
+            .add_systems(
+                Update,
+                write_bindings.run_if(changed_after_added::<Bindings>),
+            );
+    }
+}
+
+const BINDINGS_NAME: &str = "controls.ron";
+
+// Stick deflection that counts as a push
+const STICK_THRESHOLD: f32 = 0.5;
+
+// What the player can do, the systems ask Actions instead of the keyboard or the gamepad
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    NextDuck,
+    PreviousDuck,
+    Undo,
+    Restart,
+    Hint,
+    Autoplay,
+    PauseAutoplay,
+    StepAutoplay,
+    SlowerAutoplay,
+    FasterAutoplay,
+    SaveReplay,
+    PlayReplay,
+    PreviousLevel,
+    NextLevel,
+    Confirm,
+    Controls,
+    // Leaves the controls menu
+    Back,
+}
+
+impl Action {
+    pub const ALL: [Action; 21] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::NextDuck,
+        Action::PreviousDuck,
+        Action::Undo,
+        Action::Restart,
+        Action::Hint,
+        Action::Autoplay,
+        Action::PauseAutoplay,
+        Action::StepAutoplay,
+        Action::SlowerAutoplay,
+        Action::FasterAutoplay,
+        Action::SaveReplay,
+        Action::PlayReplay,
+        Action::PreviousLevel,
+        Action::NextLevel,
+        Action::Confirm,
+        Action::Controls,
+        Action::Back,
+    ];
+
+    // Shown in the controls menu
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::MoveUp => "Move up",
+            Action::MoveDown => "Move down",
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::NextDuck => "Next duck",
+            Action::PreviousDuck => "Previous duck",
+            Action::Undo => "Undo",
+            Action::Restart => "Restart",
+            Action::Hint => "Hint",
+            Action::Autoplay => "Watch the solution",
+            Action::PauseAutoplay => "Pause autoplay",
+            Action::StepAutoplay => "Step autoplay",
+            Action::SlowerAutoplay => "Slower autoplay",
+            Action::FasterAutoplay => "Faster autoplay",
+            Action::SaveReplay => "Save replay",
+            Action::PlayReplay => "Play replay",
+            Action::PreviousLevel => "Previous level",
+            Action::NextLevel => "Next level",
+            Action::Confirm => "Confirm",
+            Action::Controls => "Controls",
+            Action::Back => "Back",
+        }
+    }
+
+    pub fn direction(self) -> Option<utils::Direction> {
+        match self {
+            Action::MoveUp => Some(utils::Direction::Up),
+            Action::MoveDown => Some(utils::Direction::Down),
+            Action::MoveLeft => Some(utils::Direction::Left),
+            Action::MoveRight => Some(utils::Direction::Right),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct KeyBinding {
+    pub key: KeyCode,
+    // Shift has to be held, and not held for the bindings without it
+    #[serde(default)]
+    pub shift: bool,
+}
+
+impl KeyBinding {
+    const fn new(key: KeyCode) -> Self {
+        Self { key, shift: false }
+    }
+
+    const fn shifted(key: KeyCode) -> Self {
+        Self { key, shift: true }
+    }
+
+    pub fn name(&self) -> String {
+        if self.shift {
+            format!("Shift+{}", key_name(self.key))
+        } else {
+            key_name(self.key)
+        }
+    }
+}
+
+pub fn key_name(key: KeyCode) -> String {
+    let name = match key {
+        KeyCode::BracketLeft => "[",
+        KeyCode::BracketRight => "]",
+        KeyCode::Minus => "-",
+        KeyCode::Equal => "=",
+        KeyCode::Period => ".",
+        KeyCode::Comma => ",",
+        KeyCode::Slash => "/",
+        KeyCode::Backslash => "\\",
+        KeyCode::Semicolon => ";",
+        KeyCode::Quote => "'",
+        KeyCode::Backquote => "`",
+        KeyCode::ArrowUp => "Up",
+        KeyCode::ArrowDown => "Down",
+        KeyCode::ArrowLeft => "Left",
+        KeyCode::ArrowRight => "Right",
+        KeyCode::Escape => "Esc",
+        _ => {
+            let name = format!("{:?}", key);
+            return name
+                .trim_start_matches("Key")
+                .trim_start_matches("Digit")
+                .to_string();
+        }
+    };
+    name.to_string()
+}
+
+#[derive(Resource, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bindings {
+    pub keys: BTreeMap<Action, Vec<KeyBinding>>,
+    pub gamepad: BTreeMap<Action, Vec<GamepadButtonType>>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let keys = BTreeMap::from([
+            (
+                Action::MoveUp,
+                vec![
+                    KeyBinding::new(KeyCode::KeyW),
+                    KeyBinding::new(KeyCode::ArrowUp),
+                ],
+            ),
+            (
+                Action::MoveDown,
+                vec![
+                    KeyBinding::new(KeyCode::KeyS),
+                    KeyBinding::new(KeyCode::ArrowDown),
+                ],
+            ),
+            (
+                Action::MoveLeft,
+                vec![
+                    KeyBinding::new(KeyCode::KeyA),
+                    KeyBinding::new(KeyCode::ArrowLeft),
+                ],
+            ),
+            (
+                Action::MoveRight,
+                vec![
+                    KeyBinding::new(KeyCode::KeyD),
+                    KeyBinding::new(KeyCode::ArrowRight),
+                ],
+            ),
+            (Action::NextDuck, vec![KeyBinding::new(KeyCode::Tab)]),
+            (
+                Action::PreviousDuck,
+                vec![KeyBinding::shifted(KeyCode::Tab)],
+            ),
+            (Action::Undo, vec![KeyBinding::new(KeyCode::KeyZ)]),
+            (Action::Restart, vec![KeyBinding::new(KeyCode::KeyR)]),
+            (Action::Hint, vec![KeyBinding::new(KeyCode::KeyH)]),
+            (Action::Autoplay, vec![KeyBinding::new(KeyCode::KeyP)]),
+            (Action::PauseAutoplay, vec![KeyBinding::new(KeyCode::Space)]),
+            (Action::StepAutoplay, vec![KeyBinding::new(KeyCode::Period)]),
+            (
+                Action::SlowerAutoplay,
+                vec![KeyBinding::new(KeyCode::Minus)],
+            ),
+            (
+                Action::FasterAutoplay,
+                vec![KeyBinding::new(KeyCode::Equal)],
+            ),
+            (Action::SaveReplay, vec![KeyBinding::new(KeyCode::F5)]),
+            (Action::PlayReplay, vec![KeyBinding::new(KeyCode::F9)]),
+            (
+                Action::PreviousLevel,
+                vec![KeyBinding::new(KeyCode::BracketLeft)],
+            ),
+            (
+                Action::NextLevel,
+                vec![KeyBinding::new(KeyCode::BracketRight)],
+            ),
+            (Action::Confirm, vec![KeyBinding::new(KeyCode::Enter)]),
+            (Action::Controls, vec![KeyBinding::new(KeyCode::F1)]),
+            (Action::Back, vec![KeyBinding::new(KeyCode::Escape)]),
+        ]);
+        let gamepad = BTreeMap::from([
+            (Action::MoveUp, vec![GamepadButtonType::DPadUp]),
+            (Action::MoveDown, vec![GamepadButtonType::DPadDown]),
+            (Action::MoveLeft, vec![GamepadButtonType::DPadLeft]),
+            (Action::MoveRight, vec![GamepadButtonType::DPadRight]),
+            (Action::NextDuck, vec![GamepadButtonType::RightTrigger]),
+            (Action::PreviousDuck, vec![GamepadButtonType::LeftTrigger]),
+            (Action::Undo, vec![GamepadButtonType::East]),
+            (Action::Restart, vec![GamepadButtonType::North]),
+            (
+                Action::Confirm,
+                vec![GamepadButtonType::South, GamepadButtonType::Start],
+            ),
+            (Action::Controls, vec![GamepadButtonType::Select]),
+            (Action::Back, vec![GamepadButtonType::East]),
+        ]);
+        Self { keys, gamepad }
+    }
+}
+
+impl Bindings {
+    // Actions added after the bindings were saved get their default bindings
+    fn load() -> Self {
+        let default = Self::default();
+        match load_ron::<Bindings>(BINDINGS_NAME) {
+            Some(mut bindings) => {
+                // An action left without keys, by an older version, can't be used or rebound
+                for (action, keys) in default.keys {
+                    let saved = bindings.keys.entry(action).or_default();
+                    if saved.is_empty() {
+                        *saved = keys;
+                    }
+                }
+                for (action, buttons) in default.gamepad {
+                    bindings.gamepad.entry(action).or_insert(buttons);
+                }
+                bindings
+            }
+            None => default,
+        }
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyBinding] {
+        self.keys.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn gamepad_just_pressed(&self, action: Action, input: &ButtonInput<GamepadButton>) -> bool {
+        self.gamepad.get(&action).is_some_and(|buttons| {
+            buttons
+                .iter()
+                .any(|&button| gamepad_just_pressed(input, button))
+        })
+    }
+
+    // The main key of the action, for hints
+    pub fn key_name(&self, action: Action) -> String {
+        self.keys(action)
+            .first()
+            .map_or("?".to_string(), KeyBinding::name)
+    }
+
+    // Whether any action uses the key, with or without shift
+    pub fn is_bound(&self, key: KeyCode) -> bool {
+        self.keys
+            .values()
+            .flatten()
+            .any(|binding| binding.key == key)
+    }
+
+    // Replace the main key of the action, other actions lose the key
+    // Refused when the key is the last one of another action, that action is returned
+    pub fn rebind(&mut self, action: Action, binding: KeyBinding) -> Result<(), Action> {
+        if let Some((&other, _)) = self
+            .keys
+            .iter()
+            .find(|(&other, keys)| other != action && keys.as_slice() == [binding])
+        {
+            return Err(other);
+        }
+        for keys in self.keys.values_mut() {
+            keys.retain(|&key| key != binding);
+        }
+        let keys = self.keys.entry(action).or_default();
+        if keys.is_empty() {
+            keys.push(binding);
+        } else {
+            keys[0] = binding;
+        }
+        Ok(())
+    }
+}
+
+fn write_bindings(bindings: Res<Bindings>) {
+    save_ron(BINDINGS_NAME, &*bindings);
+}
+
+// The actions triggered this frame
+#[derive(Resource, Default)]
+pub struct Actions {
+    just_pressed: HashSet<Action>,
+    // Taken by the controls menu while it is open, the game doesn't see them
+    menu_pressed: HashSet<Action>,
+}
+
+impl Actions {
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    pub fn direction(&self) -> utils::Direction {
+        self.just_pressed
+            .iter()
+            .find_map(|action| action.direction())
+            .unwrap_or_default()
+    }
+
+    pub fn menu_pressed(&self, action: Action) -> bool {
+        self.menu_pressed.contains(&action)
+    }
+
+    pub fn menu_direction(&self) -> utils::Direction {
+        self.menu_pressed
+            .iter()
+            .find_map(|action| action.direction())
+            .unwrap_or_default()
+    }
+}
+
+// While the controls menu is open it gets the moves, Confirm and Back, Controls still closes it
+#[derive(Resource, Default)]
+pub struct ControlsMenu {
+    pub open: bool,
+    // Waiting for the new key of the action
+    pub rebinding: Option<Action>,
+    // The key last refused for it, and the action that key is the only one of
+    pub refused: Option<(KeyBinding, Action)>,
+    // Index of the focused button in the panel
+    pub focus: usize,
+}
+
+pub fn read_actions(
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepad_input: Res<ButtonInput<GamepadButton>>,
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    bindings: Res<Bindings>,
+    controls_menu: Res<ControlsMenu>,
+    mut last_stick_direction: Local<utils::Direction>,
+    mut actions: ResMut<Actions>,
+) {
+    actions.just_pressed.clear();
+    actions.menu_pressed.clear();
+    // The key goes to the binding being changed
+    if controls_menu.rebinding.is_some() {
+        return;
+    }
+
+    let shift = keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight);
+    for (&action, key_bindings) in bindings.keys.iter() {
+        if key_bindings
+            .iter()
+            .any(|binding| binding.shift == shift && keys.just_pressed(binding.key))
+        {
+            actions.just_pressed.insert(action);
+        }
+    }
+    for &action in bindings.gamepad.keys() {
+        if bindings.gamepad_just_pressed(action, &gamepad_input) {
+            actions.just_pressed.insert(action);
+        }
+    }
+
+    // The left stick moves like the D-pad, it has to come back to the center between two pushes
+    let mut stick_direction = utils::Direction::None;
+    for gamepad in gamepads.iter() {
+        let x = axes
+            .get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX))
+            .unwrap_or(0.0);
+        let y = axes
+            .get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickY))
+            .unwrap_or(0.0);
+        if x.abs().max(y.abs()) < STICK_THRESHOLD {
+            continue;
+        }
+        stick_direction = match (x.abs() > y.abs(), x > 0.0, y > 0.0) {
+            (true, true, _) => utils::Direction::Right,
+            (true, false, _) => utils::Direction::Left,
+            (false, _, true) => utils::Direction::Up,
+            (false, _, false) => utils::Direction::Down,
+        };
+    }
+    if stick_direction != *last_stick_direction {
+        if let Some(&action) = Action::ALL
+            .iter()
+            .find(|action| action.direction() == Some(stick_direction))
+        {
+            actions.just_pressed.insert(action);
+        }
+    }
+    *last_stick_direction = stick_direction;
+
+    if controls_menu.open {
+        let pressed = std::mem::take(&mut actions.just_pressed);
+        for action in pressed {
+            if action == Action::Controls {
+                actions.just_pressed.insert(action);
+            } else if action.direction().is_some()
+                || action == Action::Confirm
+                || action == Action::Back
+            {
+                actions.menu_pressed.insert(action);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebind_moves_the_key() {
+        let mut bindings = Bindings::default();
+        assert_eq!(
+            bindings.rebind(Action::Hint, KeyBinding::new(KeyCode::ArrowUp)),
+            Ok(())
+        );
+        assert_eq!(
+            bindings.keys(Action::Hint)[0],
+            KeyBinding::new(KeyCode::ArrowUp)
+        );
+        assert_eq!(
+            bindings.keys(Action::MoveUp),
+            &[KeyBinding::new(KeyCode::KeyW)]
+        );
+    }
+
+    #[test]
+    fn rebind_keeps_the_last_key_of_other_actions() {
+        let mut bindings = Bindings::default();
+        assert_eq!(
+            bindings.rebind(Action::Hint, KeyBinding::new(KeyCode::F1)),
+            Err(Action::Controls)
+        );
+        assert_eq!(
+            bindings.keys(Action::Controls),
+            &[KeyBinding::new(KeyCode::F1)]
+        );
+        assert_eq!(
+            bindings.keys(Action::Hint),
+            &[KeyBinding::new(KeyCode::KeyH)]
+        );
+
+        // An action can still take its own key again
+        assert_eq!(
+            bindings.rebind(Action::Controls, KeyBinding::new(KeyCode::F1)),
+            Ok(())
+        );
+        assert_eq!(
+            bindings.keys(Action::Controls),
+            &[KeyBinding::new(KeyCode::F1)]
+        );
+    }
+}
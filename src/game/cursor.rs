@@ -1,4 +1,13 @@
-use super::{player::CommonDuck, *};
+use super::{
+    animation::duck_atlas,
+    autoplay::Autoplay,
+    controls::{Action, Actions, Bindings, ControlsMenu},
+    grid::GridIndex,
+    layer::Layer,
+    level::Level,
+    player::{plan_slide, CommonDuck, Landing, MoveDuck},
+    *,
+};
 use crate::game::player::Player;
 use bevy::{input::mouse::MouseButtonInput, window::PrimaryWindow};
 
@@ -11,7 +20,16 @@ impl bevy::app::Plugin for Plugin {
                 Update,
                 get_cursor_position.run_if(in_state(GameStates::Next)),
             )
-            .add_systems(Update, click_detection.run_if(in_state(GameStates::Next)));
+            .add_systems(
+                Update,
+                (
+                    click_detection,
+                    keyboard_selection,
+                    drag_to_slide.after(get_cursor_position),
+                    update_move_preview,
+                )
+                    .run_if(in_state(GameStates::Next)),
+            );
     }
 }
 
@@ -58,8 +76,8 @@ fn get_cursor_position(
                         transform: Transform {
                             translation: Vec3::new(
                                 duck_position.x,
-                                duck_position.y + SPRITE_SIZE,
-                                2.0,
+                                duck_position.y + ARROW_OVER_DUCK,
+                                Layer::Hint.z(),
                             ),
                             rotation: Quat::IDENTITY,
                             scale: Vec3::new(1.0 * RESIZE, 1.0 * RESIZE, 1.0),
@@ -68,6 +86,7 @@ fn get_cursor_position(
                         ..default()
                     },
                     ArrowHint,
+                    Layer::Hint,
                     //level::Object,
                 ));
             }
@@ -80,50 +99,356 @@ pub fn click_detection(
     // event
     mut mouse_button_input_events: EventReader<MouseButtonInput>,
     // query
-    duck_query: Query<(&CommonDuck, Entity), (With<CommonDuck>, Without<Player>)>,
     player_query: Query<Entity, With<Player>>,
     arrow_hint_query: Query<Entity, (With<ArrowHint>, With<Parent>)>,
     // resource
     cursor_position: Res<CursorPosition>,
+    grid_index: Res<GridIndex>,
     image_assets: Res<ImageAssets>,
+    controls_menu: Res<ControlsMenu>,
 ) {
+    // The clicks are for the controls menu
+    if controls_menu.open {
+        mouse_button_input_events.clear();
+        return;
+    }
     for event in mouse_button_input_events.read() {
-        if event.button == MouseButton::Left {
-            for (duck, entity) in duck_query.iter() {
-                let duck_position_v3 = logic_position_to_translation(duck.logic_position);
-                let duck_position: Vec2 = Vec2 {
-                    x: duck_position_v3.x,
-                    y: duck_position_v3.y,
-                };
-                if (cursor_position.0 - duck_position).length() < DISTANCE {
-                    commands
-                        .entity(entity)
-                        .insert(Player)
-                        .with_children(|parent| {
-                            parent.spawn((
-                                SpriteBundle {
-                                    transform: Transform {
-                                        translation: Vec3::new(0.0, 500.0, 1.0),
-                                        ..default()
-                                    },
-                                    texture: image_assets.arrow.clone(),
-                                    ..default()
-                                },
-                                ArrowHint,
-                                level::Object,
-                            ));
-                        });
-                    // Clear the previous player
-                    for entity in player_query.iter() {
-                        commands.entity(entity).remove::<Player>();
-                        commands.entity(entity).clear_children();
-                    }
-                    // Clear the previous arrow hint
-                    for entity in arrow_hint_query.iter() {
-                        commands.entity(entity).despawn();
-                    }
-                }
+        if event.button != MouseButton::Left {
+            continue;
+        }
+        if let Some(entity) = duck_at(cursor_position.0, &grid_index) {
+            if !player_query.contains(entity) {
+                select_duck(
+                    &mut commands,
+                    entity,
+                    &player_query,
+                    &arrow_hint_query,
+                    &image_assets,
+                );
+            }
+        }
+    }
+}
+
+const NUMBER_KEYS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+// Next / previous duck cycle through the ducks that can move in reading order, 1-9 choose one
+// A number key bound to an action is left to the action
+fn keyboard_selection(
+    mut commands: Commands,
+    // query
+    duck_query: Query<(&CommonDuck, Entity)>,
+    player_query: Query<Entity, With<Player>>,
+    arrow_hint_query: Query<Entity, (With<ArrowHint>, With<Parent>)>,
+    // resource
+    input: Res<ButtonInput<KeyCode>>,
+    actions: Res<Actions>,
+    bindings: Res<Bindings>,
+    controls_menu: Res<ControlsMenu>,
+    image_assets: Res<ImageAssets>,
+) {
+    // The keys are for the controls menu
+    if controls_menu.open {
+        return;
+    }
+    let mut ducks: Vec<(&CommonDuck, Entity)> = duck_query
+        .iter()
+        .filter(|(duck, _)| duck.can_move)
+        .collect();
+    if ducks.is_empty() {
+        return;
+    }
+    ducks.sort_by_key(|(duck, _)| duck.logic_position);
+
+    let current = player_query
+        .get_single()
+        .ok()
+        .and_then(|player| ducks.iter().position(|&(_, entity)| entity == player));
+    let mut chosen = None;
+    let backwards = actions.just_pressed(Action::PreviousDuck);
+    if actions.just_pressed(Action::NextDuck) || backwards {
+        chosen = Some(match (current, backwards) {
+            (Some(index), false) => (index + 1) % ducks.len(),
+            (Some(index), true) => (index + ducks.len() - 1) % ducks.len(),
+            (None, false) => 0,
+            (None, true) => ducks.len() - 1,
+        });
+    }
+    for (index, key) in NUMBER_KEYS.iter().enumerate() {
+        if input.just_pressed(*key) && index < ducks.len() && !bindings.is_bound(*key) {
+            chosen = Some(index);
+        }
+    }
+
+    if let Some(index) = chosen {
+        if current != Some(index) {
+            select_duck(
+                &mut commands,
+                ducks[index].1,
+                &player_query,
+                &arrow_hint_query,
+                &image_assets,
+            );
+        }
+    }
+}
+
+// The duck on the tile under the cursor or a finger
+pub fn duck_at(position: Vec2, grid_index: &GridIndex) -> Option<Entity> {
+    translation_to_logic_position(position.extend(0.0))
+        .and_then(|logic_position| grid_index.get(logic_position, Layer::Duck))
+}
+
+// Make the duck the player and move the arrow hint over it
+pub fn select_duck(
+    commands: &mut Commands,
+    entity: Entity,
+    player_query: &Query<Entity, With<Player>>,
+    arrow_hint_query: &Query<Entity, (With<ArrowHint>, With<Parent>)>,
+    image_assets: &ImageAssets,
+) {
+    // Clear the previous player
+    for entity in player_query.iter() {
+        commands.entity(entity).remove::<Player>();
+        commands.entity(entity).clear_children();
+    }
+    // Clear the previous arrow hint
+    for entity in arrow_hint_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    commands
+        .entity(entity)
+        .insert(Player)
+        .with_children(|parent| {
+            parent.spawn((
+                SpriteBundle {
+                    // Relative to the duck, the tip just above it
+                    transform: Transform {
+                        translation: Vec3::new(0.0, 420.0, Layer::Hint.z() - Layer::Duck.z()),
+                        ..default()
+                    },
+                    texture: image_assets.arrow.clone(),
+                    ..default()
+                },
+                ArrowHint,
+                level::Object,
+                Layer::Hint,
+            ));
+        });
+}
+
+// Dragging further than this from the duck picks a direction
+const DRAG_THRESHOLD: f32 = SPRITE_SIZE / 2.0;
+
+// Where the dragged duck would end up
+#[derive(Component)]
+pub struct GhostDuck;
+
+#[derive(Default)]
+struct Drag {
+    duck: Option<Entity>,
+    start: Vec2,
+    direction: utils::Direction,
+}
+
+// Press on a duck and drag to slide it, a ghost shows where it stops before the release
+fn drag_to_slide(
+    mut commands: Commands,
+    // query
+    duck_query: Query<(&CommonDuck, Entity)>,
+    ghost_query: Query<Entity, With<GhostDuck>>,
+    // resource
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    cursor_position: Res<CursorPosition>,
+    grid_index: Res<GridIndex>,
+    level: Res<Level>,
+    image_assets: Res<ImageAssets>,
+    autoplay: Res<Autoplay>,
+    controls_menu: Res<ControlsMenu>,
+    mut drag: Local<Drag>,
+    // event
+    mut events_move: EventWriter<MoveDuck>,
+) {
+    // The clicks are for the controls menu, a drag already going is dropped
+    if controls_menu.open {
+        if drag.duck.take().is_some() {
+            for ghost in ghost_query.iter() {
+                commands.entity(ghost).despawn();
             }
         }
+        return;
+    }
+    if mouse_input.just_pressed(MouseButton::Left) && !autoplay.is_active() {
+        *drag = Drag {
+            duck: duck_at(cursor_position.0, &grid_index)
+                .filter(|&entity| duck_query.get(entity).is_ok_and(|(duck, _)| duck.can_move)),
+            start: cursor_position.0,
+            direction: utils::Direction::None,
+        };
+    }
+    let Some(entity) = drag.duck else {
+        return;
+    };
+
+    if mouse_input.just_released(MouseButton::Left) {
+        for ghost in ghost_query.iter() {
+            commands.entity(ghost).despawn();
+        }
+        if drag.direction != utils::Direction::None {
+            events_move.send(MoveDuck {
+                duck: entity,
+                direction: drag.direction,
+            });
+        }
+        *drag = Drag::default();
+        return;
+    }
+
+    let offset = cursor_position.0 - drag.start;
+    let direction = if offset.length() < DRAG_THRESHOLD {
+        utils::Direction::None
+    } else if offset.x.abs() > offset.y.abs() {
+        if offset.x > 0.0 {
+            utils::Direction::Right
+        } else {
+            utils::Direction::Left
+        }
+    } else if offset.y > 0.0 {
+        utils::Direction::Up
+    } else {
+        utils::Direction::Down
+    };
+    if direction == drag.direction {
+        return;
+    }
+    drag.direction = direction;
+    for ghost in ghost_query.iter() {
+        commands.entity(ghost).despawn();
+    }
+    if direction == utils::Direction::None {
+        return;
+    }
+
+    // Dry-run the slide on a copy of the duck
+    let Ok((duck, _)) = duck_query.get(entity) else {
+        return;
+    };
+    let mut ghost = duck.clone();
+    let slide = plan_slide(&mut ghost, direction, &level.0);
+    let translation = logic_position_to_translation(slide.end_position);
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::WHITE.with_alpha(0.4),
+                flip_x: slide.direction == utils::Direction::Right,
+                ..default()
+            },
+            transform: Transform {
+                translation: translation.with_z(Layer::Hint.z()),
+                rotation: Quat::IDENTITY,
+                scale: Vec3::new(1.0 * RESIZE, 1.0 * RESIZE, 1.0),
+            },
+            texture: image_assets.duck_atlas.clone(),
+            ..default()
+        },
+        duck_atlas(&image_assets, &ghost),
+        GhostDuck,
+        Layer::Hint,
+    ));
+}
+
+// Faint markers where the chosen duck would stop in each direction, managed like the arrow hint
+#[derive(Component)]
+pub struct MovePreview;
+
+fn update_move_preview(
+    mut commands: Commands,
+    // query
+    player_query: Query<&CommonDuck, With<Player>>,
+    added_player_query: Query<(), Added<Player>>,
+    preview_query: Query<Entity, With<MovePreview>>,
+    mut removed_players: RemovedComponents<Player>,
+    // resource
+    level: Res<Level>,
+    image_assets: Res<ImageAssets>,
+) {
+    let player_changed = !added_player_query.is_empty() || removed_players.read().count() > 0;
+    if !level.is_changed() && !player_changed {
+        return;
+    }
+    for entity in preview_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    let Ok(duck) = player_query.get_single() else {
+        return;
+    };
+    if !duck.can_move {
+        return;
+    }
+
+    for direction in utils::Direction::ALL {
+        let mut ghost = duck.clone();
+        let slide = plan_slide(&mut ghost, direction, &level.0);
+        if slide.end_position == duck.logic_position {
+            continue;
+        }
+        let translation = logic_position_to_translation(slide.end_position);
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::WHITE.with_alpha(0.2),
+                    ..default()
+                },
+                transform: Transform {
+                    translation: translation.with_z(Layer::Hint.z()),
+                    rotation: Quat::IDENTITY,
+                    scale: Vec3::new(1.0 * RESIZE, 1.0 * RESIZE, 1.0),
+                },
+                texture: image_assets.duck_atlas.clone(),
+                ..default()
+            },
+            duck_atlas(&image_assets, &ghost),
+            MovePreview,
+            Layer::Hint,
+        ));
+
+        // Small icon in the corner of the marker
+        let icon = match slide.landing {
+            Landing::Stops => None,
+            Landing::EatsBread => Some((image_assets.bread.clone(), Color::WHITE)),
+            Landing::EatsMoldyBread => Some((image_assets.bread.clone(), MOLD_COLOR)),
+            Landing::Sinks => Some((image_assets.water.clone(), Color::WHITE)),
+        };
+        if let Some((texture, color)) = icon {
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite { color, ..default() },
+                    transform: Transform {
+                        translation: Vec3::new(
+                            translation.x + SPRITE_SIZE / 4.0,
+                            translation.y + SPRITE_SIZE / 4.0,
+                            // Over the marker
+                            Layer::Hint.z() + 0.1,
+                        ),
+                        rotation: Quat::IDENTITY,
+                        scale: Vec3::new(0.4 * RESIZE, 0.4 * RESIZE, 1.0),
+                    },
+                    texture,
+                    ..default()
+                },
+                MovePreview,
+                Layer::Hint,
+            ));
+        }
     }
 }
@@ -0,0 +1,367 @@
+use std::sync::Arc;
+
+use super::{level::SymbolType::*, *};
+
+// What the goals are checked against after every move
+pub struct BoardState<'a> {
+    pub level: &'a [Vec<char>],
+    pub config: &'a level::LevelConfig,
+    pub moves: usize,
+    pub total_bread: i32,
+}
+
+impl BoardState<'_> {
+    fn count_symbols(&self, symbols: &[char]) -> usize {
+        self.level
+            .iter()
+            .flatten()
+            .filter(|&&ch| symbols.contains(&ch))
+            .count()
+    }
+
+    fn count_ducks(&self) -> usize {
+        self.level
+            .iter()
+            .flatten()
+            .filter(|&&ch| is_duck(ch))
+            .count()
+    }
+
+    // Hungry ducks that can still move and eat
+    fn count_hungry_ducks(&self) -> usize {
+        self.count_symbols(&[DuckOnIce.get_symbol(), DuckOnBreakingIce.get_symbol()])
+    }
+}
+
+pub fn is_duck(symbol: char) -> bool {
+    [
+        DuckOnIce.get_symbol(),
+        StuffedDuckOnIce.get_symbol(),
+        DuckOnWater.get_symbol(),
+        DuckOnBreakingIce.get_symbol(),
+        SickDuckOnIce.get_symbol(),
+    ]
+    .contains(&symbol)
+}
+
+pub trait WinCondition: Send + Sync {
+    fn is_met(&self, board: &BoardState) -> bool;
+    // Shown in the HUD
+    fn progress(&self, board: &BoardState) -> String;
+    // Constraints like a move limit can't win a level on their own
+    fn is_constraint(&self) -> bool {
+        false
+    }
+    // Cheap check that the goal can never be met from this board, whatever the next moves are
+    fn is_lost(&self, _board: &BoardState) -> bool {
+        false
+    }
+}
+
+// "!goal bread", the default goal
+pub struct AllBreadEaten;
+
+impl WinCondition for AllBreadEaten {
+    fn is_met(&self, board: &BoardState) -> bool {
+        board.count_symbols(&[BreadOnIce.get_symbol()]) == 0
+    }
+
+    fn progress(&self, board: &BoardState) -> String {
+        let bread_left = board.count_symbols(&[BreadOnIce.get_symbol()]) as i32;
+        format!("{}/{}", board.total_bread - bread_left, board.total_bread)
+    }
+
+    // Bread is left but no hungry duck can move anymore
+    fn is_lost(&self, board: &BoardState) -> bool {
+        !self.is_met(board) && board.count_hungry_ducks() == 0
+    }
+}
+
+// "!goal nests"
+pub struct AllDucksOnNests;
+
+impl AllDucksOnNests {
+    fn ducks_on_nests(board: &BoardState) -> usize {
+        board
+            .config
+            .nests
+            .iter()
+            .filter(|&&(row, col)| is_duck(board.level[row][col]))
+            .count()
+    }
+}
+
+impl WinCondition for AllDucksOnNests {
+    fn is_met(&self, board: &BoardState) -> bool {
+        let ducks = board.count_ducks();
+        ducks > 0 && Self::ducks_on_nests(board) == ducks
+    }
+
+    fn progress(&self, board: &BoardState) -> String {
+        format!(
+            "Nests {}/{}",
+            Self::ducks_on_nests(board),
+            board.count_ducks()
+        )
+    }
+
+    // A duck that can't move is stuck off the nests
+    fn is_lost(&self, board: &BoardState) -> bool {
+        board.level.iter().enumerate().any(|(row_index, row)| {
+            row.iter().enumerate().any(|(col_index, &ch)| {
+                [DuckOnWater.get_symbol(), SickDuckOnIce.get_symbol()].contains(&ch)
+                    && !board.config.nests.contains(&(row_index, col_index))
+            })
+        })
+    }
+}
+
+// "!goal stuffed N"
+pub struct StuffedDucks(pub usize);
+
+impl WinCondition for StuffedDucks {
+    fn is_met(&self, board: &BoardState) -> bool {
+        board.count_symbols(&[StuffedDuckOnIce.get_symbol(), DuckOnWater.get_symbol()]) == self.0
+    }
+
+    fn progress(&self, board: &BoardState) -> String {
+        format!(
+            "Stuffed {}/{}",
+            board.count_symbols(&[StuffedDuckOnIce.get_symbol(), DuckOnWater.get_symbol()]),
+            self.0
+        )
+    }
+
+    // Ducks never get hungry again, and only hungry ducks can get stuffed
+    fn is_lost(&self, board: &BoardState) -> bool {
+        let stuffed =
+            board.count_symbols(&[StuffedDuckOnIce.get_symbol(), DuckOnWater.get_symbol()]);
+        stuffed > self.0 || stuffed + board.count_hungry_ducks() < self.0
+    }
+}
+
+// "!goal moves K"
+pub struct MoveLimit(pub usize);
+
+impl WinCondition for MoveLimit {
+    fn is_met(&self, board: &BoardState) -> bool {
+        board.moves <= self.0
+    }
+
+    fn progress(&self, board: &BoardState) -> String {
+        format!("Moves {}/{}", board.moves, self.0)
+    }
+
+    fn is_lost(&self, board: &BoardState) -> bool {
+        !self.is_met(board)
+    }
+
+    fn is_constraint(&self) -> bool {
+        true
+    }
+}
+
+// "!goal afloat N", at least N ducks must stay out of the water
+pub struct DucksAfloat(pub usize);
+
+impl DucksAfloat {
+    fn ducks_afloat(board: &BoardState) -> usize {
+        board.count_ducks() - board.count_symbols(&[DuckOnWater.get_symbol()])
+    }
+}
+
+impl WinCondition for DucksAfloat {
+    fn is_met(&self, board: &BoardState) -> bool {
+        Self::ducks_afloat(board) >= self.0
+    }
+
+    fn progress(&self, board: &BoardState) -> String {
+        format!("Afloat {}/{}", Self::ducks_afloat(board), self.0)
+    }
+
+    // Sunk ducks stay sunk
+    fn is_lost(&self, board: &BoardState) -> bool {
+        !self.is_met(board)
+    }
+
+    fn is_constraint(&self) -> bool {
+        true
+    }
+}
+
+// The words after "!goal"
+pub fn parse_goal<'a>(mut words: impl Iterator<Item = &'a str>) -> Option<Arc<dyn WinCondition>> {
+    let name = words.next()?;
+    let number = words.next().and_then(|word| word.parse::<usize>().ok());
+    let goal: Arc<dyn WinCondition> = match name {
+        "bread" => Arc::new(AllBreadEaten),
+        "nests" => Arc::new(AllDucksOnNests),
+        "stuffed" => Arc::new(StuffedDucks(number?)),
+        "moves" => Arc::new(MoveLimit(number?)),
+        "afloat" => Arc::new(DucksAfloat(number?)),
+        _ => return None,
+    };
+    Some(goal)
+}
+
+pub fn goals_met(board: &BoardState) -> bool {
+    !board.config.goals.is_empty() && board.config.goals.iter().all(|goal| goal.is_met(board))
+}
+
+pub fn goals_lost(board: &BoardState) -> bool {
+    board.config.goals.iter().any(|goal| goal.is_lost(board))
+}
+
+pub fn goals_progress(board: &BoardState) -> String {
+    board
+        .config
+        .goals
+        .iter()
+        .map(|goal| goal.progress(board))
+        .collect::<Vec<String>>()
+        .join("  ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board_state<'a>(level: &'a [Vec<char>], config: &'a level::LevelConfig) -> BoardState<'a> {
+        BoardState {
+            level,
+            config,
+            moves: 0,
+            total_bread: 1,
+        }
+    }
+
+    #[test]
+    fn goals_parse_from_their_words() {
+        let parse = |text: &str| parse_goal(text.split_whitespace());
+        for text in ["bread", "nests", "stuffed 2", "moves 3", "afloat 1"] {
+            assert!(parse(text).is_some(), "{} should parse", text);
+        }
+        for text in ["", "fly", "stuffed", "moves three", "afloat -1"] {
+            assert!(parse(text).is_none(), "{} should not parse", text);
+        }
+        assert!(parse("moves 3").unwrap().is_constraint());
+        assert!(parse("afloat 1").unwrap().is_constraint());
+        assert!(!parse("stuffed 2").unwrap().is_constraint());
+    }
+
+    #[test]
+    fn all_bread_eaten() {
+        let config = level::LevelConfig::default();
+        let hungry = board(&["@D#B@"]);
+        assert!(!AllBreadEaten.is_met(&board_state(&hungry, &config)));
+        assert!(!AllBreadEaten.is_lost(&board_state(&hungry, &config)));
+        assert_eq!(
+            AllBreadEaten.progress(&board_state(&hungry, &config)),
+            "0/1"
+        );
+
+        let eaten = board(&["@Q##@"]);
+        assert!(AllBreadEaten.is_met(&board_state(&eaten, &config)));
+        assert_eq!(AllBreadEaten.progress(&board_state(&eaten, &config)), "1/1");
+
+        let sick = board(&["@S#B@"]);
+        assert!(AllBreadEaten.is_lost(&board_state(&sick, &config)));
+    }
+
+    #[test]
+    fn all_ducks_on_nests() {
+        let config = level::LevelConfig {
+            nests: vec![(0, 2), (0, 4)],
+            ..default()
+        };
+        let nested = board(&["@#D#Q@"]);
+        assert!(AllDucksOnNests.is_met(&board_state(&nested, &config)));
+        assert_eq!(
+            AllDucksOnNests.progress(&board_state(&nested, &config)),
+            "Nests 2/2"
+        );
+
+        let away = board(&["@DN#N@"]);
+        assert!(!AllDucksOnNests.is_met(&board_state(&away, &config)));
+        assert!(!AllDucksOnNests.is_lost(&board_state(&away, &config)));
+
+        let sunk = board(&["@PN#N@"]);
+        assert!(AllDucksOnNests.is_lost(&board_state(&sunk, &config)));
+
+        let no_ducks = board(&["@#N#N@"]);
+        assert!(!AllDucksOnNests.is_met(&board_state(&no_ducks, &config)));
+    }
+
+    #[test]
+    fn stuffed_ducks() {
+        let config = level::LevelConfig::default();
+        let goal = StuffedDucks(2);
+        let met = board(&["@QPD@"]);
+        assert!(goal.is_met(&board_state(&met, &config)));
+        assert_eq!(goal.progress(&board_state(&met, &config)), "Stuffed 2/2");
+
+        let hungry = board(&["@QDD@"]);
+        assert!(!goal.is_met(&board_state(&hungry, &config)));
+        assert!(!goal.is_lost(&board_state(&hungry, &config)));
+
+        let sick = board(&["@QSS@"]);
+        assert!(goal.is_lost(&board_state(&sick, &config)));
+
+        let too_many = board(&["@QQQ@"]);
+        assert!(!goal.is_met(&board_state(&too_many, &config)));
+        assert!(goal.is_lost(&board_state(&too_many, &config)));
+    }
+
+    #[test]
+    fn move_limit() {
+        let config = level::LevelConfig::default();
+        let level = board(&["@D#B@"]);
+        let goal = MoveLimit(3);
+        let at_limit = BoardState {
+            moves: 3,
+            ..board_state(&level, &config)
+        };
+        assert!(goal.is_met(&at_limit));
+        assert_eq!(goal.progress(&at_limit), "Moves 3/3");
+        let over_limit = BoardState {
+            moves: 4,
+            ..board_state(&level, &config)
+        };
+        assert!(!goal.is_met(&over_limit));
+        assert!(goal.is_lost(&over_limit));
+    }
+
+    #[test]
+    fn ducks_afloat() {
+        let config = level::LevelConfig::default();
+        let goal = DucksAfloat(2);
+        let afloat = board(&["@DPQ@"]);
+        assert!(goal.is_met(&board_state(&afloat, &config)));
+        assert_eq!(goal.progress(&board_state(&afloat, &config)), "Afloat 2/2");
+
+        let sunk = board(&["@PPQ@"]);
+        assert!(!goal.is_met(&board_state(&sunk, &config)));
+        assert!(goal.is_lost(&board_state(&sunk, &config)));
+    }
+
+    #[test]
+    fn every_goal_has_to_be_met() {
+        let level = board(&["@Q##@"]);
+        let mut config = level::LevelConfig::default();
+        assert!(!goals_met(&board_state(&level, &config)));
+
+        config.goals = vec![
+            Arc::new(AllBreadEaten) as Arc<dyn WinCondition>,
+            Arc::new(StuffedDucks(2)),
+        ];
+        assert!(!goals_met(&board_state(&level, &config)));
+        assert!(goals_lost(&board_state(&level, &config)));
+        assert_eq!(
+            goals_progress(&board_state(&level, &config)),
+            "1/1  Stuffed 1/2"
+        );
+
+        config.goals.pop();
+        assert!(goals_met(&board_state(&level, &config)));
+    }
+}
@@ -0,0 +1,190 @@
+use bevy::utils::HashMap;
+
+use super::{layer::Layer, level::Object, player::CommonDuck, *};
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        // After Update so everything spawned, despawned or moved this frame is in
+        app.init_resource::<GridIndex>().add_systems(
+            PostUpdate,
+            (unindex_removed, index_new_objects, index_ducks).chain(),
+        );
+    }
+}
+
+type Cell = ((usize, usize), Layer);
+
+// The board objects on each tile and layer, so nobody has to search the sprites for them
+#[derive(Resource, Default)]
+pub struct GridIndex {
+    cells: HashMap<Cell, Vec<Entity>>,
+    entities: HashMap<Entity, Cell>,
+}
+
+impl GridIndex {
+    pub fn get(&self, logic_position: (usize, usize), layer: Layer) -> Option<Entity> {
+        self.get_all(logic_position, layer).first().copied()
+    }
+
+    pub fn get_all(&self, logic_position: (usize, usize), layer: Layer) -> &[Entity] {
+        self.cells
+            .get(&(logic_position, layer))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    fn insert(&mut self, entity: Entity, logic_position: (usize, usize), layer: Layer) {
+        if self.entities.get(&entity) == Some(&(logic_position, layer)) {
+            return;
+        }
+        self.remove(entity);
+        self.cells
+            .entry((logic_position, layer))
+            .or_default()
+            .push(entity);
+        self.entities.insert(entity, (logic_position, layer));
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        let Some(cell) = self.entities.remove(&entity) else {
+            return;
+        };
+        if let Some(entities) = self.cells.get_mut(&cell) {
+            entities.retain(|&other| other != entity);
+            if entities.is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+    }
+}
+
+fn unindex_removed(mut grid_index: ResMut<GridIndex>, mut removed: RemovedComponents<Layer>) {
+    for entity in removed.read() {
+        grid_index.remove(entity);
+    }
+}
+
+// Objects stay where they were spawned, children are placed relative to their parent
+// Hints sit beside the tile they point at, nothing looks them up by tile
+fn index_new_objects(
+    mut grid_index: ResMut<GridIndex>,
+    object_query: Query<
+        (Entity, &Transform, &Layer),
+        (
+            Added<Layer>,
+            With<Object>,
+            Without<CommonDuck>,
+            Without<Parent>,
+        ),
+    >,
+) {
+    for (entity, transform, &layer) in object_query.iter() {
+        if layer == Layer::Hint {
+            continue;
+        }
+        if let Some(logic_position) = translation_to_logic_position(transform.translation) {
+            grid_index.insert(entity, logic_position, layer);
+        }
+    }
+}
+
+// Ducks go by their logic position, their sprites lag behind while they slide
+fn index_ducks(
+    mut grid_index: ResMut<GridIndex>,
+    duck_query: Query<(Entity, &CommonDuck, &Layer), Changed<CommonDuck>>,
+) {
+    for (entity, duck, &layer) in duck_query.iter() {
+        grid_index.insert(entity, duck.logic_position, layer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::player::duck_from_symbol;
+
+    #[test]
+    fn insert_and_get() {
+        let mut grid_index = GridIndex::default();
+        let (bread, crumb) = (Entity::from_raw(1), Entity::from_raw(2));
+        grid_index.insert(bread, (1, 2), Layer::Item);
+        grid_index.insert(crumb, (1, 2), Layer::Item);
+        assert_eq!(grid_index.get((1, 2), Layer::Item), Some(bread));
+        assert_eq!(grid_index.get_all((1, 2), Layer::Item), &[bread, crumb]);
+        assert_eq!(grid_index.get((1, 2), Layer::Duck), None);
+        assert_eq!(grid_index.get((2, 1), Layer::Item), None);
+    }
+
+    #[test]
+    fn insert_again_moves_the_entity() {
+        let mut grid_index = GridIndex::default();
+        let duck = Entity::from_raw(1);
+        grid_index.insert(duck, (1, 1), Layer::Duck);
+        grid_index.insert(duck, (1, 1), Layer::Duck);
+        assert_eq!(grid_index.get_all((1, 1), Layer::Duck), &[duck]);
+        grid_index.insert(duck, (1, 3), Layer::Duck);
+        assert_eq!(grid_index.get((1, 1), Layer::Duck), None);
+        assert_eq!(grid_index.get((1, 3), Layer::Duck), Some(duck));
+    }
+
+    #[test]
+    fn remove_cleans_up_empty_cells() {
+        let mut grid_index = GridIndex::default();
+        let (bread, crumb) = (Entity::from_raw(1), Entity::from_raw(2));
+        grid_index.insert(bread, (1, 2), Layer::Item);
+        grid_index.insert(crumb, (1, 2), Layer::Item);
+        grid_index.remove(bread);
+        assert_eq!(grid_index.get_all((1, 2), Layer::Item), &[crumb]);
+        grid_index.remove(crumb);
+        // Removing twice does nothing
+        grid_index.remove(crumb);
+        assert!(grid_index.cells.is_empty());
+        assert!(grid_index.entities.is_empty());
+    }
+
+    #[test]
+    fn systems_follow_the_board() {
+        let mut app = App::new();
+        app.add_plugins(Plugin);
+        let bread = app
+            .world_mut()
+            .spawn((
+                Transform::from_translation(Layer::Item.translation((2, 3))),
+                Layer::Item,
+                Object,
+            ))
+            .id();
+        app.world_mut().spawn((
+            Transform::from_translation(Layer::Hint.translation((2, 3))),
+            Layer::Hint,
+            Object,
+        ));
+        let duck = app
+            .world_mut()
+            .spawn((
+                Transform::default(),
+                Layer::Duck,
+                Object,
+                duck_from_symbol('D', (1, 1)).unwrap(),
+            ))
+            .id();
+        app.update();
+        let grid_index = app.world().resource::<GridIndex>();
+        assert_eq!(grid_index.get((2, 3), Layer::Item), Some(bread));
+        assert_eq!(grid_index.get((2, 3), Layer::Hint), None);
+        assert_eq!(grid_index.get((1, 1), Layer::Duck), Some(duck));
+
+        // Ducks go by their logic position, not their sprite
+        app.world_mut()
+            .get_mut::<CommonDuck>(duck)
+            .unwrap()
+            .logic_position = (1, 4);
+        app.world_mut().despawn(bread);
+        app.update();
+        let grid_index = app.world().resource::<GridIndex>();
+        assert_eq!(grid_index.get((1, 1), Layer::Duck), None);
+        assert_eq!(grid_index.get((1, 4), Layer::Duck), Some(duck));
+        assert_eq!(grid_index.get((2, 3), Layer::Item), None);
+    }
+}
@@ -0,0 +1,114 @@
+use super::{
+    controls::{Action, Actions},
+    layer::Layer,
+    level::{CurrentLevelIndex, Level, LevelConfig, LevelStack, Object, TotalBreadCount},
+    save::SaveData,
+    solver::{Move, SolveTask, Verdict, SOLUTION_SOLVER_BUDGET},
+    *,
+};
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, show_hint.run_if(in_state(GameStates::Next)));
+    }
+}
+
+// Despawned with the other objects on the next move
+#[derive(Component)]
+pub struct Hint;
+
+// The solve runs in the background, a move or another press drops it
+fn show_hint(
+    mut commands: Commands,
+    // query
+    hint_query: Query<Entity, With<Hint>>,
+    // resource
+    actions: Res<Actions>,
+    image_assets: Res<ImageAssets>,
+    level: Res<Level>,
+    config: Res<LevelConfig>,
+    level_stack: Res<LevelStack>,
+    total_bread_count: Res<TotalBreadCount>,
+    level_index: Res<CurrentLevelIndex>,
+    mut save_data: ResMut<SaveData>,
+    mut solving: Local<Option<SolveTask>>,
+) {
+    // The board the solve was started for is gone
+    if level.is_changed() || level_stack.is_changed() {
+        *solving = None;
+    }
+    if actions.just_pressed(Action::Hint) {
+        for entity in hint_query.iter() {
+            commands.entity(entity).despawn();
+        }
+        *solving = Some(SolveTask::spawn(
+            &level.0,
+            &config,
+            level_stack.0.size().saturating_sub(1),
+            total_bread_count.0,
+            SOLUTION_SOLVER_BUDGET,
+        ));
+    }
+    let Some(verdict) = solving.as_mut().and_then(SolveTask::poll) else {
+        return;
+    };
+    *solving = None;
+    match verdict {
+        Verdict::Solvable(moves) => {
+            // Already won
+            let Some(&first_move) = moves.first() else {
+                return;
+            };
+            spawn_hint(&mut commands, &image_assets, first_move);
+            *save_data.hints_used.entry(level_index.0).or_default() += 1;
+        }
+        Verdict::Unsolvable => info!("No hint, the level can't be won from here"),
+        Verdict::Unknown => info!("No hint, the solver gave up"),
+    }
+}
+
+// An arrow over the duck to choose, another one beside it pointing the way to push
+fn spawn_hint(commands: &mut Commands, image_assets: &ImageAssets, duck_move: Move) {
+    let duck_position = logic_position_to_translation(duck_move.position);
+    let (delta_row, delta_col) = duck_move.direction.delta();
+    let arrows = [
+        (
+            Vec3::new(
+                duck_position.x,
+                duck_position.y + ARROW_OVER_DUCK,
+                Layer::Hint.z(),
+            ),
+            Quat::IDENTITY,
+        ),
+        (
+            Vec3::new(
+                duck_position.x + delta_col as f32 * SPRITE_SIZE,
+                duck_position.y - delta_row as f32 * SPRITE_SIZE,
+                Layer::Hint.z(),
+            ),
+            duck_move.direction.rotation(),
+        ),
+    ];
+    for (translation, rotation) in arrows {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: MY_ORANGE,
+                    ..default()
+                },
+                transform: Transform {
+                    translation,
+                    rotation,
+                    scale: Vec3::new(1.0 * RESIZE, 1.0 * RESIZE, 1.0),
+                },
+                texture: image_assets.arrow.clone(),
+                ..default()
+            },
+            Hint,
+            Object,
+            Layer::Hint,
+        ));
+    }
+}
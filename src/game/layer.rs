@@ -0,0 +1,29 @@
+use super::*;
+
+// Room between two layers, sprites nudged inside a layer stay under the next one
+const LAYER_GAP: f32 = 1.0;
+
+// What is drawn over what, the z of everything on the board comes from here
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Layer {
+    // Ice, water and walls
+    Floor,
+    // Bread, portals, arrows, plates, gates and nests
+    Item,
+    Duck,
+    // Arrow hints, ghosts and move previews
+    Hint,
+    // Marks, counters and particles
+    Overlay,
+}
+
+impl Layer {
+    pub fn z(self) -> f32 {
+        self as usize as f32 * LAYER_GAP
+    }
+
+    // The center of the tile on this layer
+    pub fn translation(self, logic_position: (usize, usize)) -> Vec3 {
+        logic_position_to_translation(logic_position).with_z(self.z())
+    }
+}
@@ -1,4 +1,16 @@
-use super::{cursor::ArrowHint, player::CommonDuck, ui::Won, *};
+use std::sync::Arc;
+
+use super::{
+    animation::{duck_atlas, DuckAnimation, TileKind},
+    controls::{Action, Actions},
+    cursor::ArrowHint,
+    goal::{goals_met, parse_goal, AllBreadEaten, BoardState, WinCondition},
+    layer::Layer,
+    player::{duck_from_symbol, CommonDuck, DuckId, Landing},
+    switch::{board_events, update_switches, BoardEvent},
+    ui::Won,
+    *,
+};
 use thiserror::Error;
 
 pub struct Plugin;
@@ -7,19 +19,26 @@ impl bevy::app::Plugin for Plugin {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(GameStates::Next), spawn_level)
             .init_resource::<Level>()
+            .init_resource::<LevelConfig>()
             .init_resource::<Levels>()
             .init_resource::<CurrentLevelIndex>()
             .init_resource::<BreadCount>()
             .init_resource::<TotalBreadCount>()
             .init_resource::<LevelStack>()
-            .init_resource::<BreadSumRecordStack>()
+            .init_resource::<DuckRecordStack>()
+            .init_resource::<MoveRecordStack>()
             .add_event::<PrintLevel>()
             .add_event::<UpdateLevel>()
+            .add_event::<RestartLevel>()
+            .add_event::<UndoLevel>()
             .add_systems(
                 Update,
                 (
                     print_level,
                     update_level,
+                    restart_and_undo_input
+                        .before(level_restart)
+                        .before(undo_the_level),
                     level_restart,
                     load_other_level,
                     change_level_cheats,
@@ -60,7 +79,12 @@ impl Default for Levels {
             "..\\..\\assets\\levels\\level10.txt",
             "..\\..\\assets\\levels\\level11.txt",
             "..\\..\\assets\\levels\\level12.txt",
-            "..\\..\\assets\\levels\\level13.txt"
+            "..\\..\\assets\\levels\\level13.txt",
+            "..\\..\\assets\\levels\\level14.txt",
+            "..\\..\\assets\\levels\\level15.txt",
+            "..\\..\\assets\\levels\\level16.txt",
+            "..\\..\\assets\\levels\\level17.txt",
+            "..\\..\\assets\\levels\\level18.txt"
         );
 
         #[cfg(any(target_os = "linux", target_os = "macos", target_arch = "wasm32"))]
@@ -77,7 +101,12 @@ impl Default for Levels {
             "../../assets/levels/level10.txt",
             "../../assets/levels/level11.txt",
             "../../assets/levels/level12.txt",
-            "../../assets/levels/level13.txt"
+            "../../assets/levels/level13.txt",
+            "../../assets/levels/level14.txt",
+            "../../assets/levels/level15.txt",
+            "../../assets/levels/level16.txt",
+            "../../assets/levels/level17.txt",
+            "../../assets/levels/level18.txt"
         );
 
         Self { levels }
@@ -88,20 +117,157 @@ impl Default for Levels {
 pub enum GameError {
     #[error("Fail to load level!")]
     FailToLoadLevels,
+    #[error("Portal {0} should appear exactly twice!")]
+    UnpairedPortal(char),
+    #[error("Invalid level directive: {0}")]
+    InvalidDirective(String),
 }
 
-pub fn load_level(level_index: usize, levels: Res<Levels>) -> anyhow::Result<Level> {
-    levels
+// Lines starting with '!' are directives, the rest is the board
+pub fn load_level(level_index: usize, levels: &Levels) -> anyhow::Result<(Level, LevelConfig)> {
+    let level_content = levels
         .levels
         .get(level_index - 1)
-        .map(|&level_content| {
-            let level_data: Vec<Vec<char>> = level_content
-                .lines()
-                .map(|line| line.chars().collect())
-                .collect();
-            Level(level_data)
+        .ok_or(GameError::FailToLoadLevels)?;
+    let (directives, rows): (Vec<&str>, Vec<&str>) = level_content
+        .lines()
+        .partition(|line| line.starts_with('!'));
+    let level_data: Vec<Vec<char>> = rows.iter().map(|line| line.chars().collect()).collect();
+    validate_portals(&level_data)?;
+    let config = LevelConfig::parse(&level_data, &directives)?;
+    Ok((Level(level_data), config))
+}
+
+// Every portal glyph must have exactly one partner
+fn validate_portals(level: &[Vec<char>]) -> Result<(), GameError> {
+    for portal in '1'..='9' {
+        let count = level.iter().flatten().filter(|&&ch| ch == portal).count();
+        if count != 0 && count != 2 {
+            return Err(GameError::UnpairedPortal(portal));
+        }
+    }
+    Ok(())
+}
+
+pub fn is_portal(symbol: char) -> bool {
+    ('1'..='9').contains(&symbol)
+}
+
+// The other end of the portal on the logic position
+pub fn find_portal_pair(
+    level: &[Vec<char>],
+    logic_position: (usize, usize),
+) -> Option<(usize, usize)> {
+    let portal = level[logic_position.0][logic_position.1];
+    level.iter().enumerate().find_map(|(row_index, row)| {
+        row.iter()
+            .enumerate()
+            .position(|(col_index, &ch)| ch == portal && (row_index, col_index) != logic_position)
+            .map(|col_index| (row_index, col_index))
+    })
+}
+
+// Everything about the level that isn't stored in the board symbols
+#[derive(Resource, Default, Clone)]
+pub struct LevelConfig {
+    pub plates: Vec<(usize, usize)>,
+    pub gates: Vec<(usize, usize)>,
+    pub nests: Vec<(usize, usize)>,
+    // Pressure plate and the gates it opens
+    // "!link row,col row,col ..." links the plate at the first position to the gates after it,
+    // without any link every plate opens every gate
+    pub links: Vec<((usize, usize), Vec<(usize, usize)>)>,
+    // All of them must be met to win, "!goal bread" when the level declares none
+    pub goals: Vec<Arc<dyn WinCondition>>,
+}
+
+impl LevelConfig {
+    fn parse(level: &[Vec<char>], directives: &[&str]) -> Result<LevelConfig, GameError> {
+        let find_symbols = |symbols: &[char]| -> Vec<(usize, usize)> {
+            level
+                .iter()
+                .enumerate()
+                .flat_map(|(row_index, row)| {
+                    row.iter()
+                        .enumerate()
+                        .filter(|&(_, &ch)| symbols.contains(&ch))
+                        .map(move |(col_index, _)| (row_index, col_index))
+                })
+                .collect()
+        };
+        let plates = find_symbols(&[SymbolType::PressurePlate.get_symbol()]);
+        let gates = find_symbols(&[
+            SymbolType::ClosedGate.get_symbol(),
+            SymbolType::OpenGate.get_symbol(),
+        ]);
+        let nests = find_symbols(&[SymbolType::Nest.get_symbol()]);
+
+        let mut links = Vec::new();
+        let mut goals = Vec::new();
+        for &directive in directives {
+            let invalid = || GameError::InvalidDirective(directive.to_string());
+            let mut words = directive.split_whitespace();
+            match words.next() {
+                Some("!link") => {
+                    let positions = words
+                        .map(parse_position)
+                        .collect::<Option<Vec<(usize, usize)>>>()
+                        .ok_or_else(invalid)?;
+                    let (plate, linked_gates) = positions.split_first().ok_or_else(invalid)?;
+                    if !plates.contains(plate)
+                        || linked_gates.iter().any(|gate| !gates.contains(gate))
+                    {
+                        return Err(invalid());
+                    }
+                    links.push((*plate, linked_gates.to_vec()));
+                }
+                Some("!goal") => goals.push(parse_goal(words).ok_or_else(invalid)?),
+                _ => return Err(invalid()),
+            }
+        }
+        if links.is_empty() {
+            links = plates.iter().map(|&plate| (plate, gates.clone())).collect();
+        }
+        if goals.iter().all(|goal| goal.is_constraint()) {
+            goals.insert(0, Arc::new(AllBreadEaten) as Arc<dyn WinCondition>);
+        }
+
+        Ok(LevelConfig {
+            plates,
+            gates,
+            nests,
+            links,
+            goals,
         })
-        .ok_or_else(|| GameError::FailToLoadLevels.into())
+    }
+
+    // The floor under whatever stands on the logic position
+    pub fn floor_symbol(&self, logic_position: (usize, usize)) -> SymbolType {
+        if self.plates.contains(&logic_position) {
+            SymbolType::PressurePlate
+        } else if self.gates.contains(&logic_position) {
+            SymbolType::OpenGate
+        } else if self.nests.contains(&logic_position) {
+            SymbolType::Nest
+        } else {
+            SymbolType::Ice
+        }
+    }
+
+    // Put back the floor of the tiles ducks left
+    pub fn restore_floor(&self, level: &mut [Vec<char>]) {
+        for &(row, col) in self.plates.iter().chain(&self.gates).chain(&self.nests) {
+            if level[row][col] == SymbolType::Ice.get_symbol() {
+                level[row][col] = self.floor_symbol((row, col)).get_symbol();
+            }
+        }
+    }
+}
+
+// "row,col" counted from 0
+fn parse_position(word: &str) -> Option<(usize, usize)> {
+    let (row, col) = word.split_once(',')?;
+    Some((row.trim().parse().ok()?, col.trim().parse().ok()?))
 }
 
 #[derive(Resource)]
@@ -113,11 +279,48 @@ impl Default for LevelStack {
     }
 }
 
+// A duck on one board of the level stack
+#[derive(Clone, Copy, Debug)]
+pub struct DuckRecord {
+    pub id: DuckId,
+    pub logic_position: (usize, usize),
+    pub bread_sum: u32,
+}
+
+// The ducks of every board in LevelStack, undo respawns them with the same ids
 #[derive(Resource)]
-pub struct BreadSumRecordStack(pub Stack<Vec<((usize, usize), u32)>>);
-impl Default for BreadSumRecordStack {
+pub struct DuckRecordStack(pub Stack<Vec<DuckRecord>>);
+impl Default for DuckRecordStack {
     fn default() -> Self {
-        BreadSumRecordStack(Stack::new())
+        DuckRecordStack(Stack::new())
+    }
+}
+
+// The ducks are numbered in reading order when the level loads
+fn first_duck_records(level: &[Vec<char>]) -> Vec<DuckRecord> {
+    level
+        .iter()
+        .enumerate()
+        .flat_map(|(row_index, row)| {
+            row.iter()
+                .enumerate()
+                .filter_map(move |(col_index, &ch)| duck_from_symbol(ch, (row_index, col_index)))
+        })
+        .enumerate()
+        .map(|(index, duck)| DuckRecord {
+            id: DuckId(index as u32),
+            logic_position: duck.logic_position,
+            bread_sum: duck.bread_sum,
+        })
+        .collect()
+}
+
+// The moves made since the level started, undo takes them back
+#[derive(Resource)]
+pub struct MoveRecordStack(pub Stack<solver::Move>);
+impl Default for MoveRecordStack {
+    fn default() -> Self {
+        MoveRecordStack(Stack::new())
     }
 }
 
@@ -143,7 +346,6 @@ impl Default for BreadCount {
         BreadCount(1)
     }
 }
-// TODO: Layers of objects (z axis)
 pub enum SymbolType {
     Wall,
     Ice,
@@ -151,9 +353,24 @@ pub enum SymbolType {
     DuckOnIce,
     StuffedDuckOnIce,
     BreadOnIce,
+    // A hungry duck eating it gets sick and can't move anymore
+    MoldyBreadOnIce,
+    SickDuckOnIce,
     BreakingIce,
     DuckOnWater,
     DuckOnBreakingIce,
+    // '1'..='9', ducks slide in one end and out of the other one
+    Portal(char),
+    // '<' '>' 'A' 'V', ducks passing over turn to the arrow direction
+    RedirectArrow(utils::Direction),
+    // 'h' 'j' 'k' 'l', ducks can only pass in the gate direction
+    OneWayGate(utils::Direction),
+    // Opens the linked gates while a duck stands on it
+    PressurePlate,
+    ClosedGate,
+    OpenGate,
+    // Ducks can rest here, "!goal nests" wants every duck on one
+    Nest,
 }
 
 // Symbols
@@ -166,11 +383,82 @@ impl SymbolType {
             SymbolType::DuckOnIce => 'D',
             SymbolType::StuffedDuckOnIce => 'Q',
             SymbolType::BreadOnIce => 'B',
+            SymbolType::MoldyBreadOnIce => 'M',
+            SymbolType::SickDuckOnIce => 'S',
             SymbolType::BreakingIce => '*',
             SymbolType::DuckOnWater => 'P',
             SymbolType::DuckOnBreakingIce => 'O',
+            SymbolType::Portal(symbol) => symbol,
+            SymbolType::RedirectArrow(direction) => match direction {
+                utils::Direction::Up => 'A',
+                utils::Direction::Down => 'V',
+                utils::Direction::Left => '<',
+                utils::Direction::Right => '>',
+                utils::Direction::None => SymbolType::Ice.get_symbol(),
+            },
+            SymbolType::OneWayGate(direction) => match direction {
+                utils::Direction::Up => 'k',
+                utils::Direction::Down => 'j',
+                utils::Direction::Left => 'h',
+                utils::Direction::Right => 'l',
+                utils::Direction::None => SymbolType::Ice.get_symbol(),
+            },
+            SymbolType::PressurePlate => '_',
+            SymbolType::ClosedGate => '=',
+            SymbolType::OpenGate => '-',
+            SymbolType::Nest => 'N',
         }
     }
+
+    pub fn from_symbol(symbol: char) -> Option<SymbolType> {
+        let symbol_type = match symbol {
+            '@' => SymbolType::Wall,
+            '#' => SymbolType::Ice,
+            '^' => SymbolType::BrokenIce,
+            'D' => SymbolType::DuckOnIce,
+            'B' => SymbolType::BreadOnIce,
+            'M' => SymbolType::MoldyBreadOnIce,
+            'S' => SymbolType::SickDuckOnIce,
+            '*' => SymbolType::BreakingIce,
+            'P' => SymbolType::DuckOnWater,
+            'O' => SymbolType::DuckOnBreakingIce,
+            'Q' => SymbolType::StuffedDuckOnIce,
+            'A' => SymbolType::RedirectArrow(utils::Direction::Up),
+            'V' => SymbolType::RedirectArrow(utils::Direction::Down),
+            '<' => SymbolType::RedirectArrow(utils::Direction::Left),
+            '>' => SymbolType::RedirectArrow(utils::Direction::Right),
+            'k' => SymbolType::OneWayGate(utils::Direction::Up),
+            'j' => SymbolType::OneWayGate(utils::Direction::Down),
+            'h' => SymbolType::OneWayGate(utils::Direction::Left),
+            'l' => SymbolType::OneWayGate(utils::Direction::Right),
+            '_' => SymbolType::PressurePlate,
+            '=' => SymbolType::ClosedGate,
+            '-' => SymbolType::OpenGate,
+            'N' => SymbolType::Nest,
+            symbol if is_portal(symbol) => SymbolType::Portal(symbol),
+            _ => return None,
+        };
+        Some(symbol_type)
+    }
+}
+
+pub fn arrow_direction(symbol: char) -> Option<utils::Direction> {
+    match SymbolType::from_symbol(symbol) {
+        Some(SymbolType::RedirectArrow(direction)) => Some(direction),
+        _ => None,
+    }
+}
+
+pub fn gate_direction(symbol: char) -> Option<utils::Direction> {
+    match SymbolType::from_symbol(symbol) {
+        Some(SymbolType::OneWayGate(direction)) => Some(direction),
+        _ => None,
+    }
+}
+
+// Ducks slide over these tiles but never stop on them
+pub fn is_pass_through(symbol: char) -> bool {
+    is_portal(symbol) || arrow_direction(symbol).is_some() || gate_direction(symbol).is_some()
 }
 
 #[derive(Component)]
@@ -187,8 +475,22 @@ pub struct Object;
 #[derive(Event, Default)]
 pub struct PrintLevel;
 
+// From the controls or the on-screen buttons
+#[derive(Event, Default)]
+pub struct RestartLevel;
+
 #[derive(Event, Default)]
-pub struct UpdateLevel;
+pub struct UndoLevel;
+
+// Sent after a duck is pushed, with the move for the replay and where the duck ended up
+// The id is for what outlives the board, like the narration, the move goes by position, see Move
+#[derive(Event)]
+pub struct UpdateLevel {
+    pub duck: DuckId,
+    pub duck_move: solver::Move,
+    pub end_position: (usize, usize),
+    pub landing: Landing,
+}
 
 // pub fn load_level_from_file(file_path: &str) -> Result<Level, std::io::Error> {
 //     let contents = fs::read_to_string(file_path)?;
@@ -210,138 +512,303 @@ fn spawn_level(
     mut total_bread_count: ResMut<TotalBreadCount>,
     levels: Res<Levels>,
     mut level_stack: ResMut<LevelStack>,
-    mut bread_sum_record_stack: ResMut<BreadSumRecordStack>,
+    mut duck_record_stack: ResMut<DuckRecordStack>,
+    mut move_record_stack: ResMut<MoveRecordStack>,
     // event
     mut events: EventWriter<Won>,
 ) {
     // Load the level from a .txt file
-    if let Ok(level) = load_level(level_index.0, levels) {
-        // clear the stack
-        level_stack.0.clear();
-        bread_sum_record_stack.0.clear();
+    let (mut level, config) = match load_level(level_index.0, &levels) {
+        Ok(level) => level,
+        Err(err) => {
+            error!("{}", err);
+            return;
+        }
+    };
+    // clear the stack
+    level_stack.0.clear();
+    duck_record_stack.0.clear();
+    move_record_stack.0.clear();
 
-        spawn_sprites(
-            &mut commands,
-            &level.0,
-            &image_assets,
-            level_index.0,
-            &mut bread_count,
-            &mut events,
-            true,
-        );
-        level_stack.0.push(level.0.clone());
-        commands.insert_resource(level);
-        total_bread_count.0 = bread_count.0;
+    update_switches(&mut level.0, &config);
+    duck_record_stack.0.push(first_duck_records(&level.0));
+    spawn_sprites(
+        &mut commands,
+        &level.0,
+        &config,
+        &image_assets,
+        level_index.0,
+        &mut bread_count,
+        duck_record_stack.0.peek().map(Vec::as_slice),
+    );
+    level_stack.0.push(level.0.clone());
+    total_bread_count.0 = bread_count.0;
+    check_goals(&level.0, &config, 0, total_bread_count.0, &mut events);
+    commands.insert_resource(level);
+    commands.insert_resource(config);
+}
+
+// Send Won when the board meets every goal of the level
+fn check_goals(
+    level: &[Vec<char>],
+    config: &LevelConfig,
+    moves: usize,
+    total_bread: i32,
+    events: &mut EventWriter<Won>,
+) {
+    let board = BoardState {
+        level,
+        config,
+        moves,
+        total_bread,
+    };
+    if goals_met(&board) {
+        events.send(Won);
     }
 }
 
-fn update_level(
+pub fn update_level(
     mut commands: Commands,
     // event
     mut events_update: EventReader<UpdateLevel>,
     mut events: EventWriter<Won>,
+    mut events_board: EventWriter<BoardEvent>,
     // add the objects that won't be despawn to the filter
     object_query: Query<Entity, (With<Object>, Without<CommonDuck>, Without<ArrowHint>)>,
+    duck_query: Query<(&DuckId, &CommonDuck)>,
     // resource
     image_assets: Res<ImageAssets>,
-    level: Res<Level>,
+    mut level: ResMut<Level>,
+    config: Res<LevelConfig>,
     level_index: Res<CurrentLevelIndex>,
     mut bread_count: ResMut<BreadCount>,
+    total_bread_count: Res<TotalBreadCount>,
     mut level_stack: ResMut<LevelStack>,
-    mut bread_sum_record_stack: ResMut<BreadSumRecordStack>,
+    mut duck_record_stack: ResMut<DuckRecordStack>,
+    mut move_record_stack: ResMut<MoveRecordStack>,
 ) {
-    for _ in events_update.read() {
+    for UpdateLevel { duck_move, .. } in events_update.read() {
         // Do not despawn ducks, update the translations of ducks
         // Do not despawn the arrow hint
         for object in &object_query {
             commands.entity(object).despawn();
         }
+        update_switches(&mut level.0, &config);
+        if let Some(before) = level_stack.0.peek() {
+            events_board.send_batch(board_events(before, &level.0, &config));
+        }
         level_stack.0.push(level.0.clone());
-        let record: Vec<((usize, usize), u32)> = vec![];
-        bread_sum_record_stack.0.push(record);
+        // The pushed duck has already moved
+        let mut records: Vec<DuckRecord> = duck_query
+            .iter()
+            .map(|(&id, duck)| DuckRecord {
+                id,
+                logic_position: duck.logic_position,
+                bread_sum: duck.bread_sum,
+            })
+            .collect();
+        records.sort_by_key(|record| record.id);
+        duck_record_stack.0.push(records);
+        move_record_stack.0.push(*duck_move);
         spawn_sprites(
             &mut commands,
             &level.0,
+            &config,
             &image_assets,
             level_index.0,
             &mut bread_count,
+            None,
+        );
+        check_goals(
+            &level.0,
+            &config,
+            level_stack.0.size() - 1,
+            total_bread_count.0,
             &mut events,
-            false,
         );
     }
 }
 
-fn spawn_object(commands: &mut Commands, position: Vec3, sprite: Handle<Image>) {
+// Terrain from the tile atlas
+fn spawn_tile(commands: &mut Commands, position: Vec3, tile: TileKind, image_assets: &ImageAssets) {
+    commands.spawn((
+        SpriteBundle {
+            texture: image_assets.tile_atlas.clone(),
+            transform: Transform {
+                translation: position.with_z(Layer::Floor.z()),
+                rotation: Quat::IDENTITY,
+                scale: Vec3::new(1.0 * RESIZE, 1.0 * RESIZE, 1.0),
+            },
+            ..default()
+        },
+        tile.atlas(image_assets, position),
+        Object,
+        Layer::Floor,
+        theme::Tile,
+    ));
+}
+
+pub fn spawn_upper_object(
+    commands: &mut Commands,
+    position: Vec3,
+    sprite: Handle<Image>,
+    layer: Layer,
+) {
     commands.spawn((
         SpriteBundle {
             texture: sprite,
             transform: Transform {
-                translation: position,
+                translation: position.with_z(layer.z()),
                 rotation: Quat::IDENTITY,
                 scale: Vec3::new(1.0 * RESIZE, 1.0 * RESIZE, 1.0),
             },
             ..default()
         },
         Object,
+        layer,
     ));
 }
 
-pub fn spawn_upper_object(commands: &mut Commands, position: Vec3, sprite: Handle<Image>) {
+// Tell the portal pairs apart, the portal art is white so it takes the color as it is
+pub const PORTAL_COLORS: [Color; 6] = [
+    MY_ORANGE,
+    Color::srgb(90.0 / 255.0, 170.0 / 255.0, 230.0 / 255.0),
+    Color::srgb(150.0 / 255.0, 200.0 / 255.0, 80.0 / 255.0),
+    Color::srgb(190.0 / 255.0, 120.0 / 255.0, 220.0 / 255.0),
+    Color::srgb(240.0 / 255.0, 110.0 / 255.0, 150.0 / 255.0),
+    Color::srgb(245.0 / 255.0, 215.0 / 255.0, 70.0 / 255.0),
+];
+
+fn spawn_portal(commands: &mut Commands, position: Vec3, sprite: Handle<Image>, symbol: char) {
+    let pair_index = symbol.to_digit(10).unwrap_or(1) as usize - 1;
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: PORTAL_COLORS[pair_index % PORTAL_COLORS.len()],
+                ..default()
+            },
+            texture: sprite,
+            transform: Transform {
+                translation: position.with_z(Layer::Item.z()),
+                rotation: Quat::IDENTITY,
+                scale: Vec3::new(1.5 * RESIZE, 1.5 * RESIZE, 1.0),
+            },
+            ..default()
+        },
+        Object,
+        Layer::Item,
+    ));
+}
+
+const GATE_COLOR: Color = MY_ORANGE;
+pub const NEST_COLOR: Color = Color::srgb(240.0 / 255.0, 215.0 / 255.0, 140.0 / 255.0);
+
+// Ice with the plate, gate or nest built into it, they're items so they sit under the ducks
+fn spawn_floor(
+    commands: &mut Commands,
+    position: Vec3,
+    floor: SymbolType,
+    image_assets: &Res<ImageAssets>,
+) {
+    spawn_tile(commands, position, TileKind::Ice, image_assets);
+    let (sprite, color) = match floor {
+        SymbolType::PressurePlate => (image_assets.plate.clone(), MY_BROWN),
+        SymbolType::ClosedGate => (image_assets.wall.clone(), GATE_COLOR),
+        SymbolType::OpenGate => (image_assets.wall.clone(), GATE_COLOR.with_alpha(0.3)),
+        SymbolType::Nest => (image_assets.nest.clone(), NEST_COLOR),
+        _ => return,
+    };
     commands.spawn((
         SpriteBundle {
+            sprite: Sprite { color, ..default() },
             texture: sprite,
             transform: Transform {
-                translation: Vec3::new(position.x, position.y, position.z + 1.0),
+                translation: position.with_z(Layer::Item.z()),
                 rotation: Quat::IDENTITY,
                 scale: Vec3::new(1.0 * RESIZE, 1.0 * RESIZE, 1.0),
             },
             ..default()
         },
         Object,
+        Layer::Item,
+    ));
+}
+
+// Arrow sprite turned to the direction, orange for redirect arrows and brown for one-way gates
+fn spawn_direction_sign(
+    commands: &mut Commands,
+    position: Vec3,
+    sprite: Handle<Image>,
+    direction: utils::Direction,
+    color: Color,
+) {
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite { color, ..default() },
+            texture: sprite,
+            transform: Transform {
+                translation: position.with_z(Layer::Item.z()),
+                rotation: direction.rotation(),
+                scale: Vec3::new(1.0 * RESIZE, 1.0 * RESIZE, 1.0),
+            },
+            ..default()
+        },
+        Object,
+        Layer::Item,
     ));
 }
 
 #[derive(Bundle)]
 struct DuckBundle {
     sprite: SpriteBundle,
+    atlas: TextureAtlas,
+    animation: DuckAnimation,
     marker: CommonDuck,
+    id: DuckId,
     obj: Object,
+    layer: Layer,
 }
 
 fn spawn_duck(
     commands: &mut Commands,
     position: Vec3,
-    sprite: Handle<Image>,
-    click_hint: Handle<Image>,
-    logic_position: (usize, usize),
+    image_assets: &ImageAssets,
+    duck: CommonDuck,
+    id: DuckId,
     level_index: usize,
-    is_stuffed: bool,
-    can_move: bool,
 ) {
     commands.spawn(DuckBundle {
         sprite: SpriteBundle {
+            sprite: Sprite {
+                color: if duck.is_sick {
+                    MOLD_COLOR
+                } else {
+                    Color::WHITE
+                },
+                ..default()
+            },
             transform: Transform {
-                translation: Vec3::new(position.x, position.y, 1.0),
+                translation: position.with_z(Layer::Duck.z()),
                 rotation: Quat::IDENTITY,
                 scale: Vec3::new(1.0 * RESIZE, 1.0 * RESIZE, 1.0),
             },
-            texture: sprite,
+            texture: image_assets.duck_atlas.clone(),
             ..default()
         },
-        marker: CommonDuck {
-            logic_position,
-            can_move,
-            bread_sum: if is_stuffed { 1 } else { 0 },
-            belly_capacity: 1,
-        },
+        atlas: duck_atlas(image_assets, &duck),
+        animation: DuckAnimation::default(),
+        marker: duck,
+        id,
         obj: Object,
+        layer: Layer::Duck,
     });
     // Show click hint
     if level_index == 1 {
         spawn_upper_object(
             commands,
-            Vec3::new(position.x + 120.0, position.y - 120.0, 1.0),
-            click_hint,
+            Vec3::new(position.x + 120.0, position.y - 120.0, 0.0),
+            image_assets.click_hint.clone(),
+            Layer::Hint,
         );
     }
 }
@@ -349,117 +816,133 @@ fn spawn_duck(
 fn spawn_sprites(
     commands: &mut Commands,
     level: &[Vec<char>],
+    config: &LevelConfig,
     image_assets: &Res<ImageAssets>,
     level_index: usize,
     bread_count: &mut ResMut<BreadCount>,
-    // event
-    events: &mut EventWriter<Won>,
-    // when updates, do not respawn ducks
-    should_respawn_duck: bool,
+    // The ducks to respawn with their ids, None keeps the ducks as they are
+    ducks: Option<&[DuckRecord]>,
 ) {
     bread_count.0 = 0;
     // spawn the sprites
     for (row_index, row) in level.iter().enumerate() {
         for (col_index, &ch) in row.iter().enumerate() {
             let position = logic_position_to_translation((row_index, col_index));
-            let object_type = match ch {
-                '@' => SymbolType::Wall,
-                '#' => SymbolType::Ice,
-                '^' => SymbolType::BrokenIce,
-                'D' => SymbolType::DuckOnIce,
-                'B' => SymbolType::BreadOnIce,
-                '*' => SymbolType::BreakingIce,
-                'P' => SymbolType::DuckOnWater,
-                'O' => SymbolType::DuckOnBreakingIce,
-                'Q' => SymbolType::StuffedDuckOnIce,
-                _ => continue,
+            let Some(object_type) = SymbolType::from_symbol(ch) else {
+                continue;
             };
+            let record = ducks.and_then(|ducks| {
+                ducks
+                    .iter()
+                    .find(|record| record.logic_position == (row_index, col_index))
+            });
+            if let (Some(record), Some(duck)) =
+                (record, duck_from_symbol(ch, (row_index, col_index)))
+            {
+                // The belly comes from the record, the symbol only tells stuffed or not
+                let duck = CommonDuck {
+                    bread_sum: record.bread_sum,
+                    ..duck
+                };
+                spawn_duck(
+                    commands,
+                    position,
+                    image_assets,
+                    duck,
+                    record.id,
+                    level_index,
+                );
+            }
 
             match object_type {
                 SymbolType::Wall => {
-                    spawn_object(commands, position, image_assets.wall.clone());
+                    spawn_tile(commands, position, TileKind::Wall, image_assets);
                 }
                 SymbolType::Ice => {
-                    spawn_object(commands, position, image_assets.ice.clone());
+                    spawn_tile(commands, position, TileKind::Ice, image_assets);
                 }
                 SymbolType::BrokenIce => {
-                    spawn_object(commands, position, image_assets.water.clone());
+                    spawn_tile(commands, position, TileKind::Water, image_assets);
                 }
-                SymbolType::DuckOnIce => {
-                    spawn_object(commands, position, image_assets.ice.clone());
-                    if should_respawn_duck {
-                        spawn_duck(
-                            commands,
-                            position,
-                            image_assets.duck.clone(),
-                            image_assets.click_hint.clone(),
-                            (row_index, col_index),
-                            level_index,
-                            false,
-                            true,
-                        );
-                    }
-                }
-                SymbolType::StuffedDuckOnIce => {
-                    spawn_object(commands, position, image_assets.ice.clone());
-                    if should_respawn_duck {
-                        spawn_duck(
-                            commands,
-                            position,
-                            image_assets.stuffed_duck.clone(),
-                            image_assets.click_hint.clone(),
-                            (row_index, col_index),
-                            level_index,
-                            true,
-                            true,
-                        );
-                    }
+                // The plate, gate or nest under the duck stays visible
+                SymbolType::DuckOnIce
+                | SymbolType::StuffedDuckOnIce
+                | SymbolType::SickDuckOnIce => {
+                    spawn_floor(
+                        commands,
+                        position,
+                        config.floor_symbol((row_index, col_index)),
+                        image_assets,
+                    );
                 }
                 SymbolType::BreadOnIce => {
                     bread_count.0 += 1;
-                    spawn_object(commands, position, image_assets.ice.clone());
-                    spawn_upper_object(commands, position, image_assets.bread.clone());
+                    spawn_tile(commands, position, TileKind::Ice, image_assets);
+                    spawn_upper_object(commands, position, image_assets.bread.clone(), Layer::Item);
+                }
+                // Moldy bread doesn't count, ducks had better leave it alone
+                SymbolType::MoldyBreadOnIce => {
+                    spawn_tile(commands, position, TileKind::Ice, image_assets);
+                    commands.spawn((
+                        SpriteBundle {
+                            sprite: Sprite {
+                                color: MOLD_COLOR,
+                                ..default()
+                            },
+                            texture: image_assets.bread.clone(),
+                            transform: Transform {
+                                translation: position.with_z(Layer::Item.z()),
+                                rotation: Quat::IDENTITY,
+                                scale: Vec3::new(1.0 * RESIZE, 1.0 * RESIZE, 1.0),
+                            },
+                            ..default()
+                        },
+                        Object,
+                        Layer::Item,
+                    ));
                 }
                 SymbolType::BreakingIce => {
-                    spawn_object(commands, position, image_assets.breaking_ice.clone());
+                    spawn_tile(commands, position, TileKind::BreakingIce, image_assets);
                 }
                 SymbolType::DuckOnWater => {
-                    spawn_object(commands, position, image_assets.water.clone());
-                    if should_respawn_duck {
-                        spawn_duck(
-                            commands,
-                            position,
-                            image_assets.stuffed_duck.clone(),
-                            image_assets.click_hint.clone(),
-                            (row_index, col_index),
-                            level_index,
-                            true,
-                            false,
-                        );
-                    }
+                    spawn_tile(commands, position, TileKind::Water, image_assets);
                 }
                 SymbolType::DuckOnBreakingIce => {
-                    spawn_object(commands, position, image_assets.breaking_ice.clone());
-                    if should_respawn_duck {
-                        spawn_duck(
-                            commands,
-                            position,
-                            image_assets.duck.clone(),
-                            image_assets.click_hint.clone(),
-                            (row_index, col_index),
-                            level_index,
-                            false,
-                            true,
-                        );
-                    }
+                    spawn_tile(commands, position, TileKind::BreakingIce, image_assets);
+                }
+                SymbolType::Portal(symbol) => {
+                    spawn_tile(commands, position, TileKind::Ice, image_assets);
+                    spawn_portal(commands, position, image_assets.portal.clone(), symbol);
+                }
+                SymbolType::RedirectArrow(direction) => {
+                    spawn_tile(commands, position, TileKind::Ice, image_assets);
+                    spawn_direction_sign(
+                        commands,
+                        position,
+                        image_assets.arrow.clone(),
+                        direction,
+                        Color::WHITE,
+                    );
+                }
+                SymbolType::OneWayGate(direction) => {
+                    spawn_tile(commands, position, TileKind::Ice, image_assets);
+                    spawn_direction_sign(
+                        commands,
+                        position,
+                        image_assets.arrow.clone(),
+                        direction,
+                        MY_BROWN,
+                    );
+                }
+                SymbolType::PressurePlate
+                | SymbolType::ClosedGate
+                | SymbolType::OpenGate
+                | SymbolType::Nest => {
+                    spawn_floor(commands, position, object_type, image_assets);
                 }
             };
         }
     }
-
-    if bread_count.0 == 0 {
-        events.send(Won);
-    }
 }
 
 #[allow(dead_code)]
@@ -479,24 +962,39 @@ pub fn print_level(
     }
 }
 
+fn restart_and_undo_input(
+    actions: Res<Actions>,
+    mut events_restart: EventWriter<RestartLevel>,
+    mut events_undo: EventWriter<UndoLevel>,
+) {
+    if actions.just_pressed(Action::Restart) {
+        events_restart.send(RestartLevel);
+    }
+    if actions.just_pressed(Action::Undo) {
+        events_undo.send(UndoLevel);
+    }
+}
+
 fn level_restart(
     mut commands: Commands,
     // query
     object_query: Query<Entity, With<Object>>,
     ui_query: Query<Entity, With<ui::MutUI>>,
     // resource
-    input: Res<ButtonInput<KeyCode>>,
     image_assets: Res<ImageAssets>,
     bread_count: ResMut<BreadCount>,
     total_bread_count: ResMut<TotalBreadCount>,
     level_index: Res<CurrentLevelIndex>,
     levels: Res<Levels>,
     level_stack: ResMut<LevelStack>,
-    bread_sum_record_stack: ResMut<BreadSumRecordStack>,
+    duck_record_stack: ResMut<DuckRecordStack>,
+    move_record_stack: ResMut<MoveRecordStack>,
     // event
+    mut events_restart: EventReader<RestartLevel>,
     events: EventWriter<Won>,
 ) {
-    if input.just_pressed(KeyCode::KeyR) {
+    if !events_restart.is_empty() {
+        events_restart.clear();
         // Despawn level elements
         for object in &object_query {
             commands.entity(object).despawn();
@@ -513,7 +1011,8 @@ fn level_restart(
             total_bread_count,
             levels,
             level_stack,
-            bread_sum_record_stack,
+            duck_record_stack,
+            move_record_stack,
             events,
         );
     }
@@ -530,7 +1029,8 @@ fn load_other_level(
     total_bread_count: ResMut<TotalBreadCount>,
     levels: Res<Levels>,
     level_stack: ResMut<LevelStack>,
-    bread_sum_record_stack: ResMut<BreadSumRecordStack>,
+    duck_record_stack: ResMut<DuckRecordStack>,
+    move_record_stack: ResMut<MoveRecordStack>,
     // event
     events: EventWriter<Won>,
 ) {
@@ -547,7 +1047,8 @@ fn load_other_level(
             total_bread_count,
             levels,
             level_stack,
-            bread_sum_record_stack,
+            duck_record_stack,
+            move_record_stack,
             events,
         )
     }
@@ -555,19 +1056,19 @@ fn load_other_level(
 
 // Cheat codes for skipping levels
 fn change_level_cheats(
-    input: Res<ButtonInput<KeyCode>>,
+    actions: Res<Actions>,
     levels: Res<Levels>,
     mut level_index: ResMut<CurrentLevelIndex>,
 ) {
     let origin_index = level_index.0;
-    if input.just_pressed(KeyCode::BracketLeft) && level_index.0 > 1 {
+    if actions.just_pressed(Action::PreviousLevel) && level_index.0 > 1 {
         level_index.0 -= 1;
     }
-    if input.just_pressed(KeyCode::BracketRight) {
+    if actions.just_pressed(Action::NextLevel) {
         level_index.0 += 1;
     }
     // Handle invalid level index
-    if load_level(level_index.0, levels).is_err() {
+    if load_level(level_index.0, &levels).is_err() {
         //info!("Invalid level index");
         level_index.0 = origin_index;
     }
@@ -576,52 +1077,151 @@ fn change_level_cheats(
 // Undo
 fn undo_the_level(
     mut commands: Commands,
-    input: Res<ButtonInput<KeyCode>>,
+    mut events_undo: EventReader<UndoLevel>,
     mut level_stack: ResMut<LevelStack>,
-    mut bread_sum_record_stack: ResMut<BreadSumRecordStack>,
+    mut duck_record_stack: ResMut<DuckRecordStack>,
     image_assets: Res<ImageAssets>,
     level_index: Res<CurrentLevelIndex>,
     mut bread_count: ResMut<BreadCount>,
+    total_bread_count: Res<TotalBreadCount>,
+    mut move_record_stack: ResMut<MoveRecordStack>,
     mut level: ResMut<Level>,
+    config: Res<LevelConfig>,
     mut events: EventWriter<Won>,
+    mut events_board: EventWriter<BoardEvent>,
     object_query: Query<Entity, With<Object>>,
 ) {
-    if input.just_pressed(KeyCode::KeyZ) && level_stack.0.size() >= 2 {
+    if events_undo.is_empty() {
+        return;
+    }
+    events_undo.clear();
+    if level_stack.0.size() >= 2 {
         level_stack.0.pop();
-        level.0 = level_stack.0.peek().unwrap().clone();
-        if bread_sum_record_stack.0.size() >= 1 {
-            bread_sum_record_stack.0.pop();
-        }
+        let before = std::mem::replace(&mut level.0, level_stack.0.peek().unwrap().clone());
+        update_switches(&mut level.0, &config);
+        events_board.send_batch(board_events(&before, &level.0, &config));
+        duck_record_stack.0.pop();
+        move_record_stack.0.pop();
         for object in &object_query {
             commands.entity(object).despawn();
         }
         spawn_sprites(
             &mut commands,
             &level.0,
+            &config,
             &image_assets,
             level_index.0,
             &mut bread_count,
+            duck_record_stack.0.peek().map(Vec::as_slice),
+        );
+        check_goals(
+            &level.0,
+            &config,
+            level_stack.0.size() - 1,
+            total_bread_count.0,
             &mut events,
-            true,
         );
     }
 }
 
-// TODO: specify entity type and layer
-pub fn get_entity_on_logic_position(
-    logic_position: (usize, usize),
-    query: &Query<(Entity, &Transform), With<Object>>,
-) -> Option<Entity> {
-    let entity_translation = logic_position_to_translation(logic_position)
-        + Vec3 {
-            x: 0.,
-            y: 0.,
-            z: 1.,
-        }; // quick fix for duck, remove or change it later
-    for (entity, transform) in query.iter() {
-        if transform.translation == entity_translation {
-            return Some(entity);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_level_loads() {
+        let levels = Levels::default();
+        for level_index in 1..=levels.levels.len() {
+            if let Err(err) = load_level(level_index, &levels) {
+                panic!("Level {} fails to load: {}", level_index, err);
+            }
+        }
+    }
+
+    #[test]
+    fn portals_come_in_pairs() {
+        assert!(validate_portals(&board(&["@D#@", "@##@"])).is_ok());
+        assert!(validate_portals(&board(&["@1#2@", "@2#1@"])).is_ok());
+        assert!(matches!(
+            validate_portals(&board(&["@1#2@", "@##1@"])),
+            Err(GameError::UnpairedPortal('2'))
+        ));
+        assert!(matches!(
+            validate_portals(&board(&["@1#1@", "@##1@"])),
+            Err(GameError::UnpairedPortal('1'))
+        ));
+    }
+
+    #[test]
+    fn portal_pair_is_the_other_end() {
+        let level = board(&["@1#2@", "@2#1@"]);
+        assert_eq!(find_portal_pair(&level, (0, 1)), Some((1, 3)));
+        assert_eq!(find_portal_pair(&level, (1, 3)), Some((0, 1)));
+        assert_eq!(find_portal_pair(&level, (0, 3)), Some((1, 1)));
+    }
+
+    #[test]
+    fn link_opens_only_its_gates() {
+        let level = board(&["@D_=#=@"]);
+        let config = LevelConfig::parse(&level, &["!link 0,2 0,5"]).unwrap();
+        assert_eq!(config.plates, vec![(0, 2)]);
+        assert_eq!(config.gates, vec![(0, 3), (0, 5)]);
+        assert_eq!(config.links, vec![((0, 2), vec![(0, 5)])]);
+    }
+
+    #[test]
+    fn without_links_every_plate_opens_every_gate() {
+        let level = board(&["@D_=#=@"]);
+        let config = LevelConfig::parse(&level, &[]).unwrap();
+        assert_eq!(config.links, vec![((0, 2), vec![(0, 3), (0, 5)])]);
+    }
+
+    #[test]
+    fn bad_directives_fail_to_parse() {
+        let level = board(&["@D_=#=@"]);
+        for directive in [
+            "!link 0,3 0,5",
+            "!link 0,2 0,4",
+            "!link 0,2 a,b",
+            "!link",
+            "!teleport",
+        ] {
+            assert!(
+                matches!(
+                    LevelConfig::parse(&level, &[directive]),
+                    Err(GameError::InvalidDirective(_))
+                ),
+                "{} should be invalid",
+                directive
+            );
         }
     }
-    None
+
+    #[test]
+    fn bread_is_the_goal_unless_the_level_sets_one() {
+        let level = board(&["@DNB@"]);
+        let first_goal_progress = |config: &LevelConfig| {
+            config.goals[0].progress(&BoardState {
+                level: &level,
+                config,
+                moves: 0,
+                total_bread: 1,
+            })
+        };
+
+        let config = LevelConfig::parse(&level, &[]).unwrap();
+        assert_eq!(config.goals.len(), 1);
+        assert_eq!(first_goal_progress(&config), "0/1");
+
+        // A move limit alone can't win the level, the bread still has to go
+        let config = LevelConfig::parse(&level, &["!goal moves 3"]).unwrap();
+        assert_eq!(config.goals.len(), 2);
+        assert_eq!(first_goal_progress(&config), "0/1");
+        assert!(config.goals[1].is_constraint());
+
+        let config = LevelConfig::parse(&level, &["!goal nests"]).unwrap();
+        assert_eq!(config.goals.len(), 1);
+        assert_eq!(config.nests, vec![(0, 2)]);
+        assert_eq!(first_goal_progress(&config), "Nests 0/1");
+    }
 }
@@ -1,21 +1,45 @@
-use bevy::{prelude::*, render::camera::ScalingMode, window::PrimaryWindow};
+use bevy::prelude::*;
 use bevy_asset_loader::prelude::*;
 use bevy_tweening::{lens::*, *};
 
+mod accessibility;
+mod animation;
 mod audio;
+mod autoplay;
+mod camera;
+mod controls;
 mod cursor;
+mod goal;
+mod grid;
+mod hint;
+mod layer;
 mod level;
+mod narration;
+mod particles;
 mod player;
+mod replay;
+mod save;
+mod settings;
+mod solver;
+mod switch;
+mod theme;
+mod touch;
 mod ui;
 mod utils;
 
 use utils::*;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub use replay::verify_replay_file;
+
 pub const RESIZE: f32 = 0.1;
 pub const SPRITE_SIZE: f32 = 640.0 * RESIZE;
+// The arrow pointing at a duck from the tile above, its tip just over the duck
+pub const ARROW_OVER_DUCK: f32 = SPRITE_SIZE * 0.875;
 
 pub const MY_ORANGE: Color = Color::srgb(222.0 / 255.0, 112.0 / 255.0, 40.0 / 255.0);
 pub const MY_BROWN: Color = Color::srgb(91.0 / 255.0, 75.0 / 255.0, 73.0 / 255.0);
+pub const MOLD_COLOR: Color = Color::srgb(140.0 / 255.0, 200.0 / 255.0, 90.0 / 255.0);
 pub const DARK_MODE_BG_COLOR: Color = Color::srgb(45.0 / 255.0, 47.0 / 255.0, 47.0 / 255.0);
 
 pub const DUCK_MOVE_MILI_SECS: u64 = 300;
@@ -37,23 +61,26 @@ impl bevy::app::Plugin for Plugin {
                 level::Plugin,
                 ui::Plugin,
                 cursor::Plugin,
-            ))
-            .add_systems(Startup, spawn_camera);
+                switch::Plugin,
+                solver::Plugin,
+                save::Plugin,
+                hint::Plugin,
+                autoplay::Plugin,
+                replay::Plugin,
+                touch::Plugin,
+                controls::Plugin,
+                settings::Plugin,
+                camera::Plugin,
+                theme::Plugin,
+                accessibility::Plugin,
+                narration::Plugin,
+                animation::Plugin,
+                particles::Plugin,
+                grid::Plugin,
+            ));
     }
 }
 
-fn spawn_camera(mut commands: Commands, window_query: Query<&Window, With<PrimaryWindow>>) {
-    let window = window_query.get_single().unwrap();
-    let mut my_2d_camera_bundle = Camera2dBundle {
-        transform: Transform::from_xyz(window.width() / 2.0, window.height() / 2.0, 0.0),
-        ..default()
-    };
-    my_2d_camera_bundle.projection.scaling_mode = ScalingMode::FixedHorizontal(1280.0);
-    commands.spawn(my_2d_camera_bundle);
-}
-
-// TODO: How to scale all the ui elements?
-
 #[derive(AssetCollection, Resource)]
 pub struct AudioAssets {
     #[asset(path = "audio/bgm.ogg")]
@@ -72,20 +99,29 @@ pub struct ImageAssets {
     arrow: Handle<Image>,
     #[asset(path = "sprites/bread.png")]
     bread: Handle<Image>,
-    #[asset(path = "sprites/breaking_ice.png")]
-    breaking_ice: Handle<Image>,
     #[asset(path = "sprites/click_hint.png")]
     click_hint: Handle<Image>,
-    #[asset(path = "sprites/duck.png")]
-    duck: Handle<Image>,
-    #[asset(path = "sprites/ice.png")]
-    ice: Handle<Image>,
-    #[asset(path = "sprites/stuffed_duck.png")]
-    stuffed_duck: Handle<Image>,
+    #[asset(path = "sprites/duck_atlas.png")]
+    duck_atlas: Handle<Image>,
+    #[asset(texture_atlas_layout(tile_size_x = 640, tile_size_y = 640, columns = 4, rows = 2))]
+    duck_layout: Handle<TextureAtlasLayout>,
+    #[asset(path = "sprites/nest.png")]
+    nest: Handle<Image>,
+    #[asset(path = "sprites/plate.png")]
+    plate: Handle<Image>,
+    #[asset(path = "sprites/portal.png")]
+    portal: Handle<Image>,
     #[asset(path = "sprites/wall.png")]
     wall: Handle<Image>,
     #[asset(path = "sprites/water.png")]
     water: Handle<Image>,
+    #[asset(path = "sprites/tile_atlas.png")]
+    tile_atlas: Handle<Image>,
+    // The same tiles in a white frame, for the high contrast palette
+    #[asset(path = "sprites/tile_atlas_outlined.png")]
+    tile_atlas_outlined: Handle<Image>,
+    #[asset(texture_atlas_layout(tile_size_x = 640, tile_size_y = 640, columns = 4, rows = 2))]
+    tile_layout: Handle<TextureAtlasLayout>,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
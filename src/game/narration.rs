@@ -0,0 +1,207 @@
+use std::collections::VecDeque;
+
+use super::{
+    accessibility::Accessibility,
+    level::{self, BreadCount, CurrentLevelIndex, Level, RestartLevel, UndoLevel, UpdateLevel},
+    player::{duck_from_symbol, Duck, Landing},
+    theme::{TextRole, Theme, ThemeRole},
+    *,
+};
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Narration>()
+            .add_systems(Startup, show_narration_log)
+            .add_systems(
+                Update,
+                (
+                    (
+                        narrate_level_changes,
+                        narrate_moves.after(level::update_level),
+                    )
+                        .run_if(in_state(GameStates::Next)),
+                    update_narration_log.run_if(
+                        resource_changed::<Narration>.or_else(resource_changed::<Accessibility>),
+                    ),
+                ),
+            );
+    }
+}
+
+// Lines kept in the log panel
+const LOG_LENGTH: usize = 6;
+
+// What happened on the board in words, for players who can't see it well
+#[derive(Resource, Default)]
+pub struct Narration {
+    lines: VecDeque<String>,
+}
+
+impl Narration {
+    pub fn say(&mut self, line: String) {
+        announce(&line);
+        self.lines.push_back(line);
+        if self.lines.len() > LOG_LENGTH {
+            self.lines.pop_front();
+        }
+    }
+}
+
+// The live region in index.html, screen readers read what is put in it
+#[cfg(target_arch = "wasm32")]
+fn announce(line: &str) {
+    if let Some(element) = web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.get_element_by_id("narration"))
+    {
+        element.set_text_content(Some(line));
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn announce(_line: &str) {}
+
+// Rows and columns are counted from 1 for people
+fn describe_position(position: (usize, usize)) -> String {
+    format!("row {} col {}", position.0 + 1, position.1 + 1)
+}
+
+fn describe_direction(direction: utils::Direction) -> &'static str {
+    match direction {
+        utils::Direction::Up => "up",
+        utils::Direction::Down => "down",
+        utils::Direction::Left => "left",
+        utils::Direction::Right => "right",
+        utils::Direction::None => "nowhere",
+    }
+}
+
+// "2 of 5 ducks stuffed, 3 bread left"
+fn describe_board(level: &[Vec<char>], bread_count: i32) -> String {
+    let ducks: Vec<bool> = level
+        .iter()
+        .enumerate()
+        .flat_map(|(row_index, row)| {
+            row.iter()
+                .enumerate()
+                .filter_map(move |(col_index, &ch)| duck_from_symbol(ch, (row_index, col_index)))
+        })
+        .map(|duck| duck.is_stuffed())
+        .collect();
+    let stuffed = ducks.iter().filter(|&&is_stuffed| is_stuffed).count();
+    format!(
+        "{} of {} ducks stuffed, {} bread left",
+        stuffed,
+        ducks.len(),
+        bread_count
+    )
+}
+
+// Tells where the pushed duck ended up, as the move reported it
+fn narrate_moves(
+    accessibility: Res<Accessibility>,
+    level: Res<Level>,
+    bread_count: Res<BreadCount>,
+    mut events_update: EventReader<UpdateLevel>,
+    mut narration: ResMut<Narration>,
+) {
+    let Some(UpdateLevel {
+        duck,
+        duck_move,
+        end_position,
+        landing,
+    }) = events_update.read().last()
+    else {
+        return;
+    };
+    if !accessibility.narration {
+        return;
+    }
+    let landing = match landing {
+        Landing::Stops => "",
+        Landing::EatsBread => " and ate bread",
+        Landing::EatsMoldyBread => " and ate moldy bread",
+        Landing::Sinks => " and sank",
+    };
+    // Ducks are counted from 1 too
+    narration.say(format!(
+        "Duck {} at {} slid {} to {}{}; {}",
+        duck.0 + 1,
+        describe_position(duck_move.position),
+        describe_direction(duck_move.direction),
+        describe_position(*end_position),
+        landing,
+        describe_board(&level.0, bread_count.0)
+    ));
+}
+
+fn narrate_level_changes(
+    accessibility: Res<Accessibility>,
+    level_index: Res<CurrentLevelIndex>,
+    mut events_restart: EventReader<RestartLevel>,
+    mut events_undo: EventReader<UndoLevel>,
+    mut narration: ResMut<Narration>,
+) {
+    let restarted = !events_restart.is_empty();
+    let undone = !events_undo.is_empty();
+    events_restart.clear();
+    events_undo.clear();
+    if !accessibility.narration {
+        return;
+    }
+    if level_index.is_changed() {
+        narration.say(format!("Level {}", level_index.0));
+    } else if restarted {
+        narration.say("Level restarted".to_string());
+    } else if undone {
+        narration.say("Move undone".to_string());
+    }
+}
+
+#[derive(Component)]
+struct NarrationLog;
+
+fn show_narration_log(mut commands: Commands, asset_server: Res<AssetServer>, theme: Res<Theme>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("fonts/NotJamChunky8.ttf"),
+                font_size: 14.0,
+                color: theme.text,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            // Below the goals and the autoplay status, they are centred across the top
+            top: Val::Px(90.0),
+            left: Val::Px(10.0),
+            max_width: Val::Px(400.0),
+            ..default()
+        }),
+        NarrationLog,
+        TextRole(ThemeRole::Text),
+    ));
+}
+
+fn update_narration_log(
+    accessibility: Res<Accessibility>,
+    narration: Res<Narration>,
+    mut log_query: Query<(&mut Text, &mut Visibility), With<NarrationLog>>,
+) {
+    for (mut text, mut visibility) in log_query.iter_mut() {
+        *visibility = if accessibility.narration {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        text.sections[0].value = narration
+            .lines
+            .iter()
+            .cloned()
+            .collect::<Vec<String>>()
+            .join("\n");
+    }
+}
@@ -0,0 +1,314 @@
+use bevy::utils::Duration;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    accessibility::Accessibility,
+    animation::{DuckAnimation, DuckClip},
+    layer::Layer,
+    level::{NEST_COLOR, PORTAL_COLORS},
+    save::{changed_after_added, load_ron, save_ron},
+    theme::{Palette, Theme},
+    ui::Won,
+    *,
+};
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<EmitParticles>()
+            .insert_resource(load_ron::<Effects>(EFFECTS_NAME).unwrap_or_default())
+            .init_resource::<ParticleRng>()
+            .add_systems(
+                Update,
+                (
+                    write_effects.run_if(changed_after_added::<Effects>),
+                    (
+                        emit_confetti,
+                        trail_sliding_ducks,
+                        emit_particles,
+                        move_particles,
+                    )
+                        .chain()
+                        .run_if(in_state(GameStates::Next)),
+                ),
+            );
+    }
+}
+
+const EFFECTS_NAME: &str = "effects.ron";
+
+// Seconds between two puffs of snow behind a sliding duck
+const SNOW_INTERVAL_SECS: f32 = 0.05;
+
+// Eye candy the player can turn off, kept apart from the accessibility options
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct Effects {
+    // Crumbs, shards, snow and confetti
+    pub particles: bool,
+}
+
+impl Default for Effects {
+    fn default() -> Self {
+        Effects { particles: true }
+    }
+}
+
+fn write_effects(effects: Res<Effects>) {
+    save_ron(EFFECTS_NAME, &*effects);
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ParticleKind {
+    // A duck eats bread
+    Crumbs,
+    // Breaking ice gives way under a duck
+    Shards,
+    // Behind a sliding duck
+    Snow,
+    // The level is won
+    Confetti,
+}
+
+const BREAD_COLOR: Color = Color::srgb(230.0 / 255.0, 190.0 / 255.0, 120.0 / 255.0);
+const SHARD_COLOR: Color = Color::srgb(160.0 / 255.0, 215.0 / 255.0, 250.0 / 255.0);
+
+// The colors of the board, only the strong colors of the theme in high contrast,
+// a single color with reduced motion so the rain stays calm
+fn confetti_colors(theme: &Theme, accessibility: &Accessibility) -> Vec<Color> {
+    if accessibility.reduced_motion {
+        vec![theme.accent]
+    } else if theme.palette == Palette::HighContrast {
+        vec![theme.text, theme.accent]
+    } else {
+        PORTAL_COLORS.into_iter().chain([NEST_COLOR]).collect()
+    }
+}
+
+// How a kind of particle looks and moves
+struct ParticleStyle {
+    count: usize,
+    // Pixels per second
+    speed: (f32, f32),
+    gravity: f32,
+    lifetime: f32,
+    size: f32,
+}
+
+impl ParticleKind {
+    fn style(self) -> ParticleStyle {
+        match self {
+            ParticleKind::Crumbs => ParticleStyle {
+                count: 10,
+                speed: (60.0, 140.0),
+                gravity: 300.0,
+                lifetime: 0.6,
+                size: 4.0,
+            },
+            ParticleKind::Shards => ParticleStyle {
+                count: 14,
+                speed: (80.0, 200.0),
+                gravity: 400.0,
+                lifetime: 0.7,
+                size: 5.0,
+            },
+            ParticleKind::Snow => ParticleStyle {
+                count: 2,
+                speed: (20.0, 50.0),
+                gravity: 0.0,
+                lifetime: 0.4,
+                size: 3.0,
+            },
+            ParticleKind::Confetti => ParticleStyle {
+                count: 80,
+                speed: (40.0, 160.0),
+                gravity: 120.0,
+                lifetime: 2.5,
+                size: 6.0,
+            },
+        }
+    }
+
+    fn color(self, rng: &mut ParticleRng, confetti: &[Color]) -> Color {
+        match self {
+            ParticleKind::Crumbs => BREAD_COLOR,
+            ParticleKind::Shards => SHARD_COLOR,
+            ParticleKind::Snow => Color::WHITE.with_alpha(0.8),
+            ParticleKind::Confetti => {
+                confetti[(rng.next() * confetti.len() as f32) as usize % confetti.len()]
+            }
+        }
+    }
+}
+
+// A burst of particles, the delay lets it wait for the duck to get there
+#[derive(Event)]
+pub struct EmitParticles {
+    pub kind: ParticleKind,
+    pub position: Vec3,
+    pub delay: Duration,
+}
+
+// Xorshift, the particles only need to look random
+#[derive(Resource)]
+struct ParticleRng(u32);
+
+impl Default for ParticleRng {
+    fn default() -> Self {
+        ParticleRng(0x9e37_79b9)
+    }
+}
+
+impl ParticleRng {
+    // In [0, 1)
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        (self.0 >> 8) as f32 / (1 << 24) as f32
+    }
+
+    fn range(&mut self, (min, max): (f32, f32)) -> f32 {
+        min + (max - min) * self.next()
+    }
+}
+
+#[derive(Component)]
+struct Particle {
+    velocity: Vec2,
+    gravity: f32,
+    lifetime: Timer,
+}
+
+fn spawn_burst(
+    commands: &mut Commands,
+    rng: &mut ParticleRng,
+    confetti: &[Color],
+    kind: ParticleKind,
+    position: Vec3,
+) {
+    let style = kind.style();
+    for _ in 0..style.count {
+        let angle = rng.next() * std::f32::consts::TAU;
+        let velocity = Vec2::from_angle(angle) * rng.range(style.speed);
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: kind.color(rng, confetti),
+                    custom_size: Some(Vec2::splat(style.size)),
+                    ..default()
+                },
+                transform: Transform::from_translation(position.with_z(Layer::Overlay.z())),
+                ..default()
+            },
+            Layer::Overlay,
+            Particle {
+                velocity,
+                gravity: style.gravity,
+                lifetime: Timer::from_seconds(style.lifetime, TimerMode::Once),
+            },
+        ));
+    }
+}
+
+fn emit_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    effects: Res<Effects>,
+    theme: Res<Theme>,
+    accessibility: Res<Accessibility>,
+    mut rng: ResMut<ParticleRng>,
+    mut events: EventReader<EmitParticles>,
+    mut pending: Local<Vec<(Timer, ParticleKind, Vec3)>>,
+) {
+    for event in events.read() {
+        if effects.particles {
+            pending.push((
+                Timer::new(event.delay, TimerMode::Once),
+                event.kind,
+                event.position,
+            ));
+        }
+    }
+    let confetti = confetti_colors(&theme, &accessibility);
+    for (timer, kind, position) in pending.iter_mut() {
+        if timer.tick(time.delta()).finished() {
+            spawn_burst(&mut commands, &mut rng, &confetti, *kind, *position);
+        }
+    }
+    pending.retain(|(timer, _, _)| !timer.finished());
+}
+
+struct SnowTimer(Timer);
+
+impl Default for SnowTimer {
+    fn default() -> Self {
+        SnowTimer(Timer::from_seconds(
+            SNOW_INTERVAL_SECS,
+            TimerMode::Repeating,
+        ))
+    }
+}
+
+// A puff every SNOW_INTERVAL_SECS whatever the frame rate
+fn trail_sliding_ducks(
+    time: Res<Time>,
+    duck_query: Query<(&Transform, &DuckAnimation)>,
+    mut snow_timer: Local<SnowTimer>,
+    mut events: EventWriter<EmitParticles>,
+) {
+    if !snow_timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+    for (transform, animation) in duck_query.iter() {
+        if animation.clip() == DuckClip::Slide {
+            events.send(EmitParticles {
+                kind: ParticleKind::Snow,
+                position: transform.translation - Vec3::new(0.0, SPRITE_SIZE * 0.4, 0.0),
+                delay: Duration::ZERO,
+            });
+        }
+    }
+}
+
+// Rains down from the top of the view
+fn emit_confetti(
+    mut events_won: EventReader<Won>,
+    camera_query: Query<&Transform, With<Camera2d>>,
+    mut events: EventWriter<EmitParticles>,
+) {
+    if events_won.read().count() == 0 {
+        return;
+    }
+    for transform in camera_query.iter() {
+        for offset in [-400.0, 0.0, 400.0] {
+            events.send(EmitParticles {
+                kind: ParticleKind::Confetti,
+                position: transform.translation + Vec3::new(offset, 300.0, 0.0),
+                delay: Duration::ZERO,
+            });
+        }
+    }
+}
+
+fn move_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut particle_query: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, mut particle, mut transform, mut sprite) in particle_query.iter_mut() {
+        if particle.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        particle.velocity.y -= particle.gravity * delta;
+        transform.translation += particle.velocity.extend(0.0) * delta;
+        // Fade out
+        let alpha = sprite.color.alpha();
+        sprite
+            .color
+            .set_alpha(alpha.min(particle.lifetime.fraction_remaining()));
+    }
+}
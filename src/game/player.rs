@@ -1,9 +1,14 @@
 use super::{
+    accessibility::Accessibility,
+    animation::{DuckAnimation, DuckClip},
     audio::PlaySFX,
-    level::{get_entity_on_logic_position, SymbolType::*, UpdateLevel},
+    grid::GridIndex,
+    layer::Layer,
+    level::{SymbolType, SymbolType::*, UpdateLevel},
+    particles::{EmitParticles, ParticleKind},
     *,
 };
-use bevy::utils::Duration;
+use bevy::utils::{Duration, HashSet};
 
 pub struct Plugin;
 
@@ -12,13 +17,15 @@ impl bevy::app::Plugin for Plugin {
         app.add_systems(
             Update,
             (
+                action_movement.before(player_movement),
                 player_movement,
                 component_animator_system::<Transform>,
                 shake_other_ducks_in_direction,
             )
                 .run_if(in_state(GameStates::Next)),
         )
-        .add_event::<ShakeOtherDucksInDir>();
+        .add_event::<ShakeOtherDucksInDir>()
+        .add_event::<MoveDuck>();
     }
 }
 
@@ -30,14 +37,21 @@ pub trait Duck {
     fn set_logic_position(&mut self, position: (usize, usize));
     fn set_can_move(&mut self, can_move: bool);
     fn eat_bread(&mut self);
+    fn is_sick(&self) -> bool;
+    fn eat_moldy_bread(&mut self);
 }
 
-#[derive(Component)]
+// Numbered when the level loads, it stays with the duck through moves and undos
+#[derive(Component, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct DuckId(pub u32);
+
+#[derive(Component, Clone)]
 pub struct CommonDuck {
     pub logic_position: (usize, usize),
     pub can_move: bool, // stuffed_duck on breaking_ice => can't move
     pub bread_sum: u32,
     pub belly_capacity: u32,
+    pub is_sick: bool, // ate moldy bread => can't move
 }
 
 impl Duck for CommonDuck {
@@ -68,74 +82,120 @@ impl Duck for CommonDuck {
     fn get_bread_sum(&self) -> u32 {
         self.bread_sum
     }
+
+    fn is_sick(&self) -> bool {
+        self.is_sick
+    }
+
+    fn eat_moldy_bread(&mut self) {
+        self.is_sick = true;
+        self.can_move = false;
+    }
+}
+
+// The duck standing on the logic position, rebuilt from its symbol
+pub fn duck_from_symbol(symbol: char, logic_position: (usize, usize)) -> Option<CommonDuck> {
+    let (is_stuffed, can_move, is_sick) = match SymbolType::from_symbol(symbol)? {
+        DuckOnIce | DuckOnBreakingIce => (false, true, false),
+        StuffedDuckOnIce => (true, true, false),
+        DuckOnWater => (true, false, false),
+        SickDuckOnIce => (false, false, true),
+        _ => return None,
+    };
+    Some(CommonDuck {
+        logic_position,
+        can_move,
+        bread_sum: if is_stuffed { 1 } else { 0 },
+        belly_capacity: 1,
+        is_sick,
+    })
 }
 
 // the chosen duck
 #[derive(Component)]
 pub struct Player;
 
+// Push a duck, sent for the chosen duck by the controls or by the autoplay
+#[derive(Event)]
+pub struct MoveDuck {
+    pub duck: Entity,
+    pub direction: utils::Direction,
+}
+
+// The chosen duck moves with the move actions, from the keyboard or the gamepad
+fn action_movement(
+    player_query: Query<Entity, With<Player>>,
+    actions: Res<controls::Actions>,
+    autoplay: Res<autoplay::Autoplay>,
+    mut events_move: EventWriter<MoveDuck>,
+) {
+    // The autoplay has the ducks
+    if autoplay.is_active() {
+        return;
+    }
+    let direction = actions.direction();
+    if direction == utils::Direction::None {
+        return;
+    }
+    if let Ok(entity) = player_query.get_single() {
+        events_move.send(MoveDuck {
+            duck: entity,
+            direction,
+        });
+    }
+}
+
 fn player_movement(
     mut commands: Commands,
     // query
-    mut player_query: Query<
-        (
-            &mut Transform,
-            &mut Sprite,
-            &mut Handle<Image>,
-            Option<&mut CommonDuck>,
-            Entity,
-        ),
-        With<Player>,
-    >,
+    mut duck_query: Query<(
+        &mut Transform,
+        &mut Sprite,
+        &mut DuckAnimation,
+        &mut CommonDuck,
+        &DuckId,
+    )>,
     // event
+    mut events_move: EventReader<MoveDuck>,
     mut events_sfx: EventWriter<PlaySFX>,
     mut events_update: EventWriter<UpdateLevel>,
     mut event_shake: EventWriter<ShakeOtherDucksInDir>,
     mut events_print: EventWriter<level::PrintLevel>,
+    mut events_particles: EventWriter<EmitParticles>,
     // resource
-    key_board_input: Res<ButtonInput<KeyCode>>,
-    level: ResMut<level::Level>,
-    asset_server: Res<AssetServer>,
+    mut level: ResMut<level::Level>,
     audio_assets: Res<AudioAssets>,
+    accessibility: Res<Accessibility>,
 ) {
-    if let Ok((transform, mut sprite, mut image, c_duck, entity)) = player_query.get_single_mut() {
-        let duck: &mut dyn Duck = c_duck.unwrap().into_inner();
+    // One move a frame, the level is updated once per move
+    let Some(&MoveDuck {
+        duck: entity,
+        direction,
+    }) = events_move.read().last()
+    else {
+        return;
+    };
+    if let Ok((transform, mut sprite, mut animation, c_duck, &id)) = duck_query.get_mut(entity) {
+        let duck: &mut dyn Duck = c_duck.into_inner();
 
         if !duck.can_move() {
             return;
         }
-        let mut direction = utils::Direction::None;
-
-        if key_board_input.just_pressed(KeyCode::ArrowLeft)
-            || key_board_input.just_pressed(KeyCode::KeyA)
-        {
-            direction = utils::Direction::Left;
-            sprite.flip_x = false;
-        }
-        if key_board_input.just_pressed(KeyCode::ArrowRight)
-            || key_board_input.just_pressed(KeyCode::KeyD)
-        {
-            direction = utils::Direction::Right;
-            sprite.flip_x = true;
-        }
-        if key_board_input.just_pressed(KeyCode::ArrowUp)
-            || key_board_input.just_pressed(KeyCode::KeyW)
-        {
-            direction = utils::Direction::Up;
-        }
-        if key_board_input.just_pressed(KeyCode::ArrowDown)
-            || key_board_input.just_pressed(KeyCode::KeyS)
-        {
-            direction = utils::Direction::Down;
-        }
         if direction != utils::Direction::None {
             let duck_bread_sum_before = duck.get_bread_sum();
             let duck_can_move_before = duck.can_move();
-            let duck_is_stuffed_before = duck.is_stuffed();
-            let end_position = slip(duck, direction, level);
+            let duck_is_sick_before = duck.is_sick();
+            let start_position = duck.get_logic_position();
+            let slide = slip(duck, direction, &mut level.0);
             let duck_bread_sum_after = duck.get_bread_sum();
             let duck_can_move_after = duck.can_move();
-            let duck_is_stuffed_after = duck.is_stuffed();
+
+            // Face the way the duck ends up sliding
+            match slide.direction {
+                utils::Direction::Left => sprite.flip_x = false,
+                utils::Direction::Right => sprite.flip_x = true,
+                _ => (),
+            }
 
             // TODO: delay it
             if duck_bread_sum_after > duck_bread_sum_before {
@@ -146,11 +206,35 @@ fn player_movement(
                 });
             }
 
-            if !duck_is_stuffed_before && duck_is_stuffed_after {
-                *image = asset_server.load("sprites/stuffed_duck.png");
+            // Slide along the path, then eat or sink where it lands
+            let slide_duration = Duration::from_millis(
+                DUCK_MOVE_MILI_SECS * slide.path.len().saturating_sub(1).max(1) as u64,
+            );
+            animation.play(
+                DuckClip::Slide,
+                Some(slide_duration),
+                DuckClip::from_landing(slide.landing),
+            );
+            let landing_particles = match slide.landing {
+                Landing::Stops => None,
+                Landing::EatsBread | Landing::EatsMoldyBread => Some(ParticleKind::Crumbs),
+                Landing::Sinks => Some(ParticleKind::Shards),
+            };
+            if let Some(kind) = landing_particles {
+                events_particles.send(EmitParticles {
+                    kind,
+                    position: Layer::Duck.translation(slide.end_position),
+                    delay: slide_duration,
+                });
             }
 
-            if duck_can_move_before && !duck_can_move_after {
+            if !duck_is_sick_before && duck.is_sick() {
+                sprite.color = MOLD_COLOR;
+                events_sfx.send(PlaySFX {
+                    source: audio_assets.eat.clone(),
+                    volume: bevy::audio::Volume::new(0.05),
+                });
+            } else if duck_can_move_before && !duck_can_move_after {
                 // play ice breaking sound
                 events_sfx.send(PlaySFX {
                     source: audio_assets.ice_breaking.clone(),
@@ -159,37 +243,17 @@ fn player_movement(
             }
 
             // Update object positions
-            duck.set_logic_position(end_position);
+            duck.set_logic_position(slide.end_position);
             // Update the translation of ducks
-            let v3 = logic_position_to_translation(end_position);
-            let tween_translation = Tween::new(
-                EaseFunction::QuadraticInOut,
-                Duration::from_millis(DUCK_MOVE_MILI_SECS),
-                TransformPositionLens {
-                    start: transform.translation,
-                    end: Vec3::new(v3.x, v3.y, 1.0),
-                },
-            )
-            .with_repeat_count(1);
-
-            // Scale the duck while moving
-            let origin_scale = Vec3::new(1.0 * RESIZE, 1.0 * RESIZE, 1.0);
-            let new_scale = transform.scale * Vec3::new(1.3, 0.7, 1.);
-            let tween_scale = Tween::new(
-                EaseFunction::QuadraticInOut,
-                Duration::from_millis(DUCK_MOVE_MILI_SECS),
-                TransformScaleLens {
-                    start: new_scale,
-                    end: origin_scale,
-                },
-            )
-            .with_repeat_count(1);
-
-            let track: Tracks<Transform> = Tracks::new(vec![tween_translation, tween_scale]);
-
-            commands.entity(entity).insert(Animator::new(track));
+            commands
+                .entity(entity)
+                .insert(Animator::new(slide_tweenable(
+                    &transform,
+                    &slide,
+                    accessibility.reduced_motion,
+                )));
             event_shake.send(ShakeOtherDucksInDir {
-                direction,
+                direction: slide.direction,
                 player_logic_position: duck.get_logic_position(),
             });
             //let v3 = logic_position_to_translation(end_position, window_query.get_single().unwrap());
@@ -201,104 +265,271 @@ fn player_movement(
                 volume: bevy::audio::Volume::new(0.4),
             });
             events_print.send(level::PrintLevel);
-            events_update.send(UpdateLevel);
+            events_update.send(UpdateLevel {
+                duck: id,
+                duck_move: solver::Move {
+                    position: start_position,
+                    direction,
+                },
+                end_position: slide.end_position,
+                landing: slide.landing,
+            });
         }
     }
 }
 
-// Slip until hitting the wall or bread
-// common duck
-fn slip(
-    duck: &mut dyn Duck,
-    direction: utils::Direction,
-    // resource
-    mut level: ResMut<level::Level>,
-) -> (usize, usize) {
-    // Up: row--, Down: row++, Left: col--, Right: col++
-    let rows = level.0.len();
-    let logic_position = duck.get_logic_position();
-    let cols = level.0[logic_position.0].len();
-    let mut position = logic_position;
-    match direction {
-        utils::Direction::Up => {
-            while position.0 > 0 && is_valid_move(level.0[position.0 - 1][position.1], duck) {
-                position.0 -= 1;
-                if collide_with_object(level.0[position.0][position.1], duck) {
-                    break;
-                }
-            }
+// Squash the duck while it slides, it shrinks into every portal and pops out of the pair
+// With reduced motion it only slides
+fn slide_tweenable(
+    transform: &Transform,
+    slide: &Slide,
+    reduced_motion: bool,
+) -> Sequence<Transform> {
+    // Split the path into runs the duck slides along without warping
+    let mut runs: Vec<Vec<Vec3>> = vec![vec![transform.translation]];
+    for waypoint in slide.path.iter().skip(1) {
+        let translation = Layer::Duck.translation(waypoint.position);
+        match runs.last_mut() {
+            Some(run) if !waypoint.warped => run.push(translation),
+            _ => runs.push(vec![translation]),
         }
-        utils::Direction::Down => {
-            while position.0 < rows - 1 && is_valid_move(level.0[position.0 + 1][position.1], duck)
-            {
-                position.0 += 1;
-                if collide_with_object(level.0[position.0][position.1], duck) {
-                    break;
-                }
+    }
+
+    let origin_scale = Vec3::new(1.0 * RESIZE, 1.0 * RESIZE, 1.0);
+    let (new_scale, warp_scale) = if reduced_motion {
+        (origin_scale, origin_scale)
+    } else {
+        (transform.scale * Vec3::new(1.3, 0.7, 1.), Vec3::ZERO)
+    };
+    let last_index = runs.len() - 1;
+    let segments: Vec<Tracks<Transform>> = runs
+        .into_iter()
+        .enumerate()
+        .map(|(index, mut run)| {
+            if run.len() == 1 {
+                run.push(run[0]);
             }
+            let tween_translations: Vec<Tween<Transform>> = run
+                .windows(2)
+                .map(|pair| {
+                    Tween::new(
+                        EaseFunction::QuadraticInOut,
+                        Duration::from_millis(DUCK_MOVE_MILI_SECS),
+                        TransformPositionLens {
+                            start: pair[0],
+                            end: pair[1],
+                        },
+                    )
+                    .with_repeat_count(1)
+                })
+                .collect();
+            let tween_scale = Tween::new(
+                EaseFunction::QuadraticInOut,
+                Duration::from_millis(DUCK_MOVE_MILI_SECS * tween_translations.len() as u64),
+                TransformScaleLens {
+                    start: if index == 0 { new_scale } else { warp_scale },
+                    end: if index == last_index {
+                        origin_scale
+                    } else {
+                        warp_scale
+                    },
+                },
+            )
+            .with_repeat_count(1);
+            let tracks: Vec<BoxedTweenable<Transform>> = vec![
+                Box::new(Sequence::new(tween_translations)),
+                Box::new(tween_scale),
+            ];
+            Tracks::new(tracks)
+        })
+        .collect();
+    Sequence::new(segments)
+}
+
+// A corner of the slide path
+pub struct Waypoint {
+    pub position: (usize, usize),
+    // came out of a portal
+    pub warped: bool,
+}
+
+// What stopped the duck, besides walls and other ducks
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Landing {
+    Stops,
+    EatsBread,
+    EatsMoldyBread,
+    // a stuffed duck breaks the ice
+    Sinks,
+}
+
+// Where the slide ends, the direction it ends in and the way there
+pub struct Slide {
+    pub end_position: (usize, usize),
+    pub direction: utils::Direction,
+    pub path: Vec<Waypoint>,
+    pub landing: Landing,
+}
+
+// The next tile to stand on, after going through portals, arrows and one-way gates
+struct Passage {
+    position: (usize, usize),
+    direction: utils::Direction,
+    waypoints: Vec<Waypoint>,
+}
+
+// Tiles already slid onto are remembered in visited, so arrows can't send a duck round in circles
+fn next_tile(
+    level: &[Vec<char>],
+    position: (usize, usize),
+    direction: utils::Direction,
+    visited: &mut HashSet<((usize, usize), utils::Direction)>,
+) -> Option<Passage> {
+    let mut direction = direction;
+    let mut waypoints = Vec::new();
+    let mut next = utils::step(level, position, direction)?;
+    loop {
+        if !visited.insert((next, direction)) {
+            return None;
         }
-        utils::Direction::Left => {
-            while position.1 > 0 && is_valid_move(level.0[position.0][position.1 - 1], duck) {
-                position.1 -= 1;
-                if collide_with_object(level.0[position.0][position.1], duck) {
-                    break;
-                }
+        let symbol = level[next.0][next.1];
+        if level::is_portal(symbol) {
+            let exit = level::find_portal_pair(level, next)?;
+            waypoints.push(Waypoint {
+                position: next,
+                warped: false,
+            });
+            waypoints.push(Waypoint {
+                position: exit,
+                warped: true,
+            });
+            next = utils::step(level, exit, direction)?;
+        } else if let Some(arrow_direction) = level::arrow_direction(symbol) {
+            waypoints.push(Waypoint {
+                position: next,
+                warped: false,
+            });
+            direction = arrow_direction;
+            next = utils::step(level, next, direction)?;
+        } else if let Some(gate_direction) = level::gate_direction(symbol) {
+            if gate_direction != direction {
+                return None;
             }
+            next = utils::step(level, next, direction)?;
+        } else {
+            return Some(Passage {
+                position: next,
+                direction,
+                waypoints,
+            });
         }
-        utils::Direction::Right => {
-            while position.1 < cols - 1 && is_valid_move(level.0[position.0][position.1 + 1], duck)
-            {
-                position.1 += 1;
-                if collide_with_object(level.0[position.0][position.1], duck) {
-                    break;
-                }
-            }
+    }
+}
+
+// Slip until hitting the wall or bread
+// common duck
+pub fn slip(duck: &mut dyn Duck, direction: utils::Direction, level: &mut [Vec<char>]) -> Slide {
+    let start_position = duck.get_logic_position();
+    let slide = plan_slide(duck, direction, level);
+    apply_slide(duck, start_position, &slide, level);
+    slide
+}
+
+// Work out the slide without touching the level, only the duck eats or sinks
+// Dry-run it on a clone of the duck for previews
+pub fn plan_slide(duck: &mut dyn Duck, direction: utils::Direction, level: &[Vec<char>]) -> Slide {
+    let mut position = duck.get_logic_position();
+    let mut direction = direction;
+    let mut landing = Landing::Stops;
+    let mut path = vec![Waypoint {
+        position,
+        warped: false,
+    }];
+    let mut visited = HashSet::new();
+    while let Some(passage) = next_tile(level, position, direction, &mut visited) {
+        if !is_valid_move(level[passage.position.0][passage.position.1], duck) {
+            break;
         }
-        _ => (),
+        path.extend(passage.waypoints);
+        position = passage.position;
+        direction = passage.direction;
+        if let Some(collision) = collide_with_object(level[position.0][position.1], duck) {
+            landing = collision;
+            break;
+        }
+    }
+    path.push(Waypoint {
+        position,
+        warped: false,
+    });
+    Slide {
+        end_position: position,
+        direction,
+        path,
+        landing,
     }
+}
 
-    // Update symbols on the level
+// Move the duck symbol on the level from the start to the end of the slide
+pub fn apply_slide(
+    duck: &dyn Duck,
+    start_position: (usize, usize),
+    slide: &Slide,
+    level: &mut [Vec<char>],
+) {
+    let position = slide.end_position;
     let mut duck_char: char = DuckOnIce.get_symbol();
     if duck.is_stuffed() {
         duck_char = StuffedDuckOnIce.get_symbol();
     }
 
-    if level.0[logic_position.0][logic_position.1] == DuckOnBreakingIce.get_symbol() {
-        level.0[logic_position.0][logic_position.1] = BreakingIce.get_symbol();
+    if level[start_position.0][start_position.1] == DuckOnBreakingIce.get_symbol() {
+        level[start_position.0][start_position.1] = BreakingIce.get_symbol();
     } else {
-        level.0[logic_position.0][logic_position.1] = Ice.get_symbol();
+        level[start_position.0][start_position.1] = Ice.get_symbol();
     }
-    if level.0[position.0][position.1] == BreakingIce.get_symbol() {
-        level.0[position.0][position.1] = DuckOnBreakingIce.get_symbol();
+    if level[position.0][position.1] == BreakingIce.get_symbol() {
+        level[position.0][position.1] = DuckOnBreakingIce.get_symbol();
     } else {
-        level.0[position.0][position.1] = duck_char;
+        level[position.0][position.1] = duck_char;
     }
-    if !duck.can_move() {
-        level.0[position.0][position.1] = DuckOnWater.get_symbol();
+    if duck.is_sick() {
+        level[position.0][position.1] = SickDuckOnIce.get_symbol();
+    } else if !duck.can_move() {
+        level[position.0][position.1] = DuckOnWater.get_symbol();
     }
-    position
 }
 
 fn is_valid_move(symbol: char, duck: &dyn Duck) -> bool {
     symbol != Wall.get_symbol()
+        && symbol != ClosedGate.get_symbol()
         && symbol != DuckOnIce.get_symbol()
         && symbol != DuckOnWater.get_symbol()
         && symbol != DuckOnBreakingIce.get_symbol()
         && symbol != StuffedDuckOnIce.get_symbol()
-        && (!duck.is_stuffed() || symbol != BreadOnIce.get_symbol())
+        && symbol != SickDuckOnIce.get_symbol()
+        && (!duck.is_stuffed()
+            || (symbol != BreadOnIce.get_symbol() && symbol != MoldyBreadOnIce.get_symbol()))
+        // ducks never stop on portals, arrows or one-way gates
+        && !level::is_pass_through(symbol)
 }
 
 // TODO: replace it with eat_bread_or_break_ice
-fn collide_with_object(symbol: char, duck: &mut dyn Duck) -> bool {
+// Some when the duck stops on the tile
+fn collide_with_object(symbol: char, duck: &mut dyn Duck) -> Option<Landing> {
     if symbol == BreadOnIce.get_symbol() {
         duck.eat_bread();
-        return true;
+        return Some(Landing::EatsBread);
+    }
+    if symbol == MoldyBreadOnIce.get_symbol() {
+        duck.eat_moldy_bread();
+        return Some(Landing::EatsMoldyBread);
     }
     if symbol == BreakingIce.get_symbol() && duck.is_stuffed() {
         duck.set_can_move(false);
-        return true;
+        return Some(Landing::Sinks);
     }
-    false
+    None
 }
 
 #[derive(Event)]
@@ -310,37 +541,35 @@ struct ShakeOtherDucksInDir {
 fn shake_other_ducks_in_direction(
     mut commands: Commands,
     level: Res<level::Level>,
-    query: Query<(Entity, &Transform), With<level::Object>>,
+    grid_index: Res<GridIndex>,
     mut events: EventReader<ShakeOtherDucksInDir>,
+    accessibility: Res<Accessibility>,
 ) {
+    if accessibility.reduced_motion {
+        events.clear();
+        return;
+    }
     for e in events.read() {
-        let direction = e.direction;
+        let mut direction = e.direction;
         let mut ducks_to_shake: Vec<Entity> = Vec::new();
         let mut position = e.player_logic_position;
-        let rows = level.0.len();
-        let cols = level.0[position.0].len();
-
-        let delta: (i32, i32) = match direction {
-            utils::Direction::Up => (-1, 0),
-            utils::Direction::Down => (1, 0),
-            utils::Direction::Left => (0, -1),
-            utils::Direction::Right => (0, 1),
-            utils::Direction::None => return,
-        };
-
-        while position.0 > 0 && position.0 < rows - 1 && position.1 > 0 && position.1 < cols - 1 {
-            position.0 = (delta.0 + position.0 as i32) as usize;
-            position.1 = (delta.1 + position.1 as i32) as usize;
+        let mut visited = HashSet::new();
+
+        // Follow the arrows and portals the same way a sliding duck would
+        while let Some(passage) = next_tile(&level.0, position, direction, &mut visited) {
+            position = passage.position;
+            direction = passage.direction;
             let symbol = level.0[position.0][position.1];
             if [
                 DuckOnBreakingIce.get_symbol(),
                 DuckOnIce.get_symbol(),
                 DuckOnWater.get_symbol(),
                 StuffedDuckOnIce.get_symbol(),
+                SickDuckOnIce.get_symbol(),
             ]
             .contains(&symbol)
             {
-                if let Some(entity) = get_entity_on_logic_position(position, &query) {
+                if let Some(entity) = grid_index.get(position, Layer::Duck) {
                     ducks_to_shake.push(entity);
                 }
             } else {
@@ -367,3 +596,95 @@ fn shake_other_ducks_in_direction(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan(rows: &[&str], position: (usize, usize), direction: utils::Direction) -> Slide {
+        let level = board(rows);
+        let mut duck = duck_from_symbol(level[position.0][position.1], position).unwrap();
+        plan_slide(&mut duck, direction, &level)
+    }
+
+    #[test]
+    fn portal_sends_the_duck_out_of_its_pair() {
+        let slide = plan(
+            &["@D#1@", "@@@@@", "@1##@"],
+            (0, 1),
+            utils::Direction::Right,
+        );
+        assert_eq!(slide.end_position, (2, 3));
+        assert_eq!(slide.direction, utils::Direction::Right);
+        let warped: Vec<(usize, usize)> = slide
+            .path
+            .iter()
+            .filter(|waypoint| waypoint.warped)
+            .map(|waypoint| waypoint.position)
+            .collect();
+        assert_eq!(warped, vec![(2, 1)]);
+    }
+
+    #[test]
+    fn portal_against_a_wall_stops_the_duck_before_it() {
+        let slide = plan(
+            &["@D#1@", "@@@@@", "@@1@@"],
+            (0, 1),
+            utils::Direction::Right,
+        );
+        assert_eq!(slide.end_position, (0, 2));
+    }
+
+    #[test]
+    fn portal_eats_the_bread_on_the_other_side() {
+        let slide = plan(&["@D1@", "@1B@"], (0, 1), utils::Direction::Right);
+        assert_eq!(slide.end_position, (1, 2));
+        assert_eq!(slide.landing, Landing::EatsBread);
+    }
+
+    #[test]
+    fn arrow_turns_the_duck() {
+        let slide = plan(
+            &["@D#V@", "@@@#@", "@@@#@", "@@@@@"],
+            (0, 1),
+            utils::Direction::Right,
+        );
+        assert_eq!(slide.end_position, (2, 3));
+        assert_eq!(slide.direction, utils::Direction::Down);
+    }
+
+    #[test]
+    fn arrows_in_a_loop_stop_the_duck() {
+        let slide = plan(
+            &["@@@@@@", "@D>#V@", "@@A#<@", "@@@@@@"],
+            (1, 1),
+            utils::Direction::Right,
+        );
+        assert_eq!(slide.end_position, (2, 3));
+    }
+
+    #[test]
+    fn one_way_gate_only_lets_ducks_through_its_way() {
+        let through = plan(&["@D#l#@"], (0, 1), utils::Direction::Right);
+        assert_eq!(through.end_position, (0, 4));
+        let against = plan(&["@#l#D@"], (0, 4), utils::Direction::Left);
+        assert_eq!(against.end_position, (0, 3));
+    }
+
+    #[test]
+    fn moldy_bread_makes_the_duck_sick() {
+        let mut level = board(&["@D#M#@"]);
+        let mut duck = duck_from_symbol('D', (0, 1)).unwrap();
+        let slide = slip(&mut duck, utils::Direction::Right, &mut level);
+        assert_eq!(slide.landing, Landing::EatsMoldyBread);
+        assert!(duck.is_sick && !duck.can_move);
+        assert_eq!(level, board(&["@##S#@"]));
+    }
+
+    #[test]
+    fn stuffed_duck_stops_before_moldy_bread() {
+        let slide = plan(&["@Q#M#@"], (0, 1), utils::Direction::Right);
+        assert_eq!(slide.end_position, (0, 2));
+        assert_eq!(slide.landing, Landing::Stops);
+    }
+}
@@ -0,0 +1,525 @@
+use std::{fmt, str::FromStr};
+
+use thiserror::Error;
+
+use super::{
+    autoplay::{self, Autoplay},
+    controls::{Action, Actions},
+    goal::{goals_met, is_duck, BoardState},
+    level::{
+        self, load_level, CurrentLevelIndex, Levels, RestartLevel, SymbolType::BreadOnIce,
+        UndoLevel, UpdateLevel,
+    },
+    save::{read_storage, write_storage},
+    solver::{apply_move, Move},
+    switch::update_switches,
+    ui::Won,
+    utils::Direction,
+    *,
+};
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PendingReplay>()
+            .init_resource::<Session>()
+            .add_systems(
+                Update,
+                (
+                    // After the move is on the board, so a win is never written without its move
+                    record_session.after(level::update_level),
+                    save_replay.after(record_session),
+                    // so the autoplay doesn't take the level change for the player leaving the level
+                    (load_replay, start_pending_replay)
+                        .chain()
+                        .after(autoplay::autoplay_controls),
+                )
+                    .run_if(in_state(GameStates::Next)),
+            );
+    }
+}
+
+const REPLAY_NAME: &str = "replay.txt";
+// Written by itself when the level is left or won, for bug reports
+const SESSION_NAME: &str = "session.txt";
+
+#[derive(Error, Debug)]
+pub enum ReplayError {
+    #[error("No replay saved")]
+    Missing,
+    #[error("Malformed replay: {0}")]
+    Malformed(String),
+    #[error("Level {0} doesn't exist!")]
+    UnknownLevel(usize),
+    #[error("Level {0} changed since the replay was recorded!")]
+    LevelChanged(usize),
+    #[error("Step {0} pushes no duck at {1:?}")]
+    NoDuck(usize, (usize, usize)),
+}
+
+// One thing the player did on the level
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Step {
+    Move(Move),
+    Undo,
+    Restart,
+}
+
+// "<row>,<col><U|D|L|R>" for a move, "undo" and "restart" for the others
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Step::Move(duck_move) => write!(
+                f,
+                "{},{}{}",
+                duck_move.position.0,
+                duck_move.position.1,
+                direction_letter(duck_move.direction)
+            ),
+            Step::Undo => write!(f, "undo"),
+            Step::Restart => write!(f, "restart"),
+        }
+    }
+}
+
+impl Step {
+    fn parse(word: &str) -> Option<Step> {
+        match word {
+            "undo" => Some(Step::Undo),
+            "restart" => Some(Step::Restart),
+            _ => {
+                let letter = word.chars().last()?;
+                let (row, col) = word[..word.len() - letter.len_utf8()].split_once(',')?;
+                Some(Step::Move(Move {
+                    position: (row.parse().ok()?, col.parse().ok()?),
+                    direction: letter_direction(letter)?,
+                }))
+            }
+        }
+    }
+}
+
+// "<level>:<level hash>:<step> ...", e.g. "3:af63bd4c8601b7be:2,4R undo 2,5D"
+// Every step of the session is kept, undone moves and restarts too
+// The steps are played from the level start, so the positions always pick the same ducks, see Move
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Replay {
+    pub level: usize,
+    pub level_hash: u64,
+    pub steps: Vec<Step>,
+}
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{:016x}:", self.level, self.level_hash)?;
+        let steps: Vec<String> = self.steps.iter().map(Step::to_string).collect();
+        write!(f, "{}", steps.join(" "))
+    }
+}
+
+impl FromStr for Replay {
+    type Err = ReplayError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let malformed = || ReplayError::Malformed(text.trim().to_string());
+        let mut parts = text.trim().splitn(3, ':');
+        let level = parts
+            .next()
+            .and_then(|part| part.parse().ok())
+            .ok_or_else(malformed)?;
+        let level_hash = parts
+            .next()
+            .and_then(|part| u64::from_str_radix(part, 16).ok())
+            .ok_or_else(malformed)?;
+        let steps = parts
+            .next()
+            .ok_or_else(malformed)?
+            .split_whitespace()
+            .map(Step::parse)
+            .collect::<Option<Vec<Step>>>()
+            .ok_or_else(malformed)?;
+        Ok(Replay {
+            level,
+            level_hash,
+            steps,
+        })
+    }
+}
+
+fn direction_letter(direction: Direction) -> char {
+    match direction {
+        Direction::Up => 'U',
+        Direction::Down => 'D',
+        Direction::Left => 'L',
+        Direction::Right => 'R',
+        Direction::None => '.',
+    }
+}
+
+fn letter_direction(letter: char) -> Option<Direction> {
+    match letter {
+        'U' => Some(Direction::Up),
+        'D' => Some(Direction::Down),
+        'L' => Some(Direction::Left),
+        'R' => Some(Direction::Right),
+        _ => None,
+    }
+}
+
+// FNV-1a, stable across builds and platforms unlike the std hasher
+// Line endings don't count, so a level checked out with CRLF hashes the same
+pub fn level_hash(level_text: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for line in level_text.lines() {
+        for byte in line.bytes().chain(std::iter::once(b'\n')) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash
+}
+
+// Check the replay was recorded on this version of the level
+fn check_level(replay: &Replay, levels: &Levels) -> Result<(), ReplayError> {
+    let level_text = replay
+        .level
+        .checked_sub(1)
+        .and_then(|index| levels.levels.get(index))
+        .ok_or(ReplayError::UnknownLevel(replay.level))?;
+    if level_hash(level_text) != replay.level_hash {
+        return Err(ReplayError::LevelChanged(replay.level));
+    }
+    Ok(())
+}
+
+// Play the steps without the game running, Ok(true) if they win the level
+// The boards are stacked like LevelStack, undo pops one and restart goes back to the first
+pub fn verify(replay: &Replay, levels: &Levels) -> anyhow::Result<bool> {
+    check_level(replay, levels)?;
+    let (level, config) = load_level(replay.level, levels)?;
+    let mut first = level.0;
+    update_switches(&mut first, &config);
+    let total_bread = first
+        .iter()
+        .flatten()
+        .filter(|&&ch| ch == BreadOnIce.get_symbol())
+        .count() as i32;
+    let mut boards = vec![first];
+    for (index, &step) in replay.steps.iter().enumerate() {
+        match step {
+            Step::Move(duck_move) => {
+                let board = boards.last().unwrap();
+                let (row, col) = duck_move.position;
+                if !board
+                    .get(row)
+                    .and_then(|line| line.get(col))
+                    .is_some_and(|&ch| is_duck(ch))
+                {
+                    return Err(ReplayError::NoDuck(index + 1, duck_move.position).into());
+                }
+                // A blocked push still counts as a move
+                let next = apply_move(board, &config, duck_move).unwrap_or_else(|| board.clone());
+                boards.push(next);
+            }
+            // Nothing to undo on the first board
+            Step::Undo if boards.len() >= 2 => {
+                boards.pop();
+            }
+            Step::Undo => (),
+            Step::Restart => boards.truncate(1),
+        }
+    }
+    Ok(goals_met(&BoardState {
+        level: boards.last().unwrap(),
+        config: &config,
+        moves: boards.len() - 1,
+        total_bread,
+    }))
+}
+
+// For "--verify-replay <file>"
+#[cfg(not(target_arch = "wasm32"))]
+pub fn verify_replay_file(path: &str) -> anyhow::Result<bool> {
+    let replay: Replay = std::fs::read_to_string(path)?.parse()?;
+    verify(&replay, &Levels::default())
+}
+
+// Everything done on the level since it was entered
+#[derive(Resource, Default)]
+struct Session {
+    level: usize,
+    steps: Vec<Step>,
+}
+
+impl Session {
+    fn replay(&self, levels: &Levels) -> Option<Replay> {
+        let level_text = levels.levels.get(self.level.checked_sub(1)?)?;
+        Some(Replay {
+            level: self.level,
+            level_hash: level_hash(level_text),
+            steps: self.steps.clone(),
+        })
+    }
+
+    fn write(&self, levels: &Levels, name: &str) {
+        let Some(replay) = self.replay(levels) else {
+            return;
+        };
+        match write_storage(name, &replay.to_string()) {
+            Ok(()) => info!("Replay saved to {}: {}", name, replay),
+            Err(err) => error!("Fail to save the replay: {}", err),
+        }
+    }
+}
+
+// Keep every move, undo and restart, the session is written out when the level is won or left
+fn record_session(
+    level_index: Res<CurrentLevelIndex>,
+    levels: Res<Levels>,
+    mut events_update: EventReader<UpdateLevel>,
+    mut events_undo: EventReader<UndoLevel>,
+    mut events_restart: EventReader<RestartLevel>,
+    mut events_won: EventReader<Won>,
+    mut session: ResMut<Session>,
+) {
+    for UpdateLevel { duck_move, .. } in events_update.read() {
+        session.steps.push(Step::Move(*duck_move));
+    }
+    // The level takes several in one frame as one
+    if events_undo.read().count() > 0 {
+        session.steps.push(Step::Undo);
+    }
+    if events_restart.read().count() > 0 {
+        session.steps.push(Step::Restart);
+    }
+    if events_won.read().count() > 0 {
+        session.write(&levels, SESSION_NAME);
+    }
+    if level_index.is_changed() {
+        if !session.steps.is_empty() {
+            session.write(&levels, SESSION_NAME);
+        }
+        *session = Session {
+            level: level_index.0,
+            steps: Vec::new(),
+        };
+    }
+}
+
+// Save the session so far as the replay to play
+fn save_replay(actions: Res<Actions>, levels: Res<Levels>, session: Res<Session>) {
+    if actions.just_pressed(Action::SaveReplay) {
+        session.write(&levels, REPLAY_NAME);
+    }
+}
+
+// Waits for the level of the replay to be loaded
+#[derive(Resource, Default)]
+struct PendingReplay(Option<Replay>);
+
+// Restart the level of the saved replay and play it
+fn load_replay(
+    actions: Res<Actions>,
+    levels: Res<Levels>,
+    mut level_index: ResMut<CurrentLevelIndex>,
+    mut pending_replay: ResMut<PendingReplay>,
+) {
+    if !actions.just_pressed(Action::PlayReplay) {
+        return;
+    }
+    let result = read_storage(REPLAY_NAME)
+        .ok_or(ReplayError::Missing)
+        .and_then(|text| text.parse::<Replay>())
+        .and_then(|replay| check_level(&replay, &levels).map(|_| replay));
+    match result {
+        Ok(replay) => {
+            // Reloads the level even if it's the current one
+            level_index.0 = replay.level;
+            level_index.set_changed();
+            pending_replay.0 = Some(replay);
+        }
+        Err(err) => error!("Fail to load the replay: {}", err),
+    }
+}
+
+fn start_pending_replay(
+    level_index: Res<CurrentLevelIndex>,
+    mut pending_replay: ResMut<PendingReplay>,
+    mut autoplay: ResMut<Autoplay>,
+) {
+    if pending_replay.0.is_none() || level_index.is_changed() {
+        return;
+    }
+    if let Some(replay) = pending_replay.0.take() {
+        autoplay.start(replay.steps);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moves(moves: &[((usize, usize), Direction)]) -> Vec<Step> {
+        moves
+            .iter()
+            .map(|&(position, direction)| {
+                Step::Move(Move {
+                    position,
+                    direction,
+                })
+            })
+            .collect()
+    }
+
+    // The fewest moves that win level 14
+    fn level14_replay(levels: &Levels) -> Replay {
+        Replay {
+            level: 14,
+            level_hash: level_hash(levels.levels[13]),
+            steps: moves(&[
+                ((1, 1), Direction::Right),
+                ((3, 6), Direction::Up),
+                ((5, 2), Direction::Up),
+                ((3, 2), Direction::Left),
+            ]),
+        }
+    }
+
+    #[test]
+    fn replay_text_round_trips() {
+        let replay = Replay {
+            level: 3,
+            level_hash: 0xaf63bd4c8601b7be,
+            steps: moves(&[((2, 4), Direction::Right), ((2, 5), Direction::Down)]),
+        };
+        assert_eq!(replay.to_string(), "3:af63bd4c8601b7be:2,4R 2,5D");
+        assert_eq!(replay.to_string().parse::<Replay>().unwrap(), replay);
+
+        let empty = Replay {
+            level: 1,
+            level_hash: 0,
+            steps: Vec::new(),
+        };
+        assert_eq!(empty.to_string(), "1:0000000000000000:");
+        assert_eq!(empty.to_string().parse::<Replay>().unwrap(), empty);
+
+        let mut steps = moves(&[((2, 4), Direction::Right)]);
+        steps.extend([Step::Undo, Step::Restart]);
+        let with_undo = Replay {
+            level: 3,
+            level_hash: 0xaf63bd4c8601b7be,
+            steps,
+        };
+        assert_eq!(
+            with_undo.to_string(),
+            "3:af63bd4c8601b7be:2,4R undo restart"
+        );
+        assert_eq!(with_undo.to_string().parse::<Replay>().unwrap(), with_undo);
+    }
+
+    #[test]
+    fn malformed_replays_fail_to_parse() {
+        for text in [
+            "",
+            "1:0",
+            "x:0:1,1R",
+            "1:zz:1,1R",
+            "1:0:1,1X",
+            "1:0:11R",
+            "1:0:a,1R",
+            "1:0:1,1R redo",
+        ] {
+            assert!(
+                matches!(text.parse::<Replay>(), Err(ReplayError::Malformed(_))),
+                "{:?} should be malformed",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn level_hash_is_fnv_and_ignores_line_endings() {
+        assert_eq!(level_hash(""), 0xcbf29ce484222325);
+        assert_eq!(level_hash("a"), 0x089bdc07b544e7b2);
+        assert_eq!(level_hash("@D#\r\n@B#"), level_hash("@D#\n@B#"));
+        assert_ne!(level_hash("@D#\n@B#"), level_hash("@B#\n@D#"));
+    }
+
+    #[test]
+    fn verify_plays_the_moves() {
+        let levels = Levels::default();
+        let replay = level14_replay(&levels);
+        assert!(verify(&replay, &levels).unwrap());
+
+        let unfinished = Replay {
+            steps: replay.steps[..3].to_vec(),
+            ..replay.clone()
+        };
+        assert!(!verify(&unfinished, &levels).unwrap());
+    }
+
+    #[test]
+    fn verify_plays_undo_and_restart() {
+        let levels = Levels::default();
+        let replay = level14_replay(&levels);
+        let solution = replay.steps.clone();
+
+        // A wrong push taken back, then the solution
+        let mut steps = moves(&[((5, 2), Direction::Up)]);
+        steps.push(Step::Undo);
+        steps.extend(solution.iter().copied());
+        let undone = Replay {
+            steps,
+            ..replay.clone()
+        };
+        assert!(verify(&undone, &levels).unwrap());
+
+        // Undoing the last move of the solution leaves the level unwon
+        let mut steps = solution.clone();
+        steps.push(Step::Undo);
+        let last_undone = Replay {
+            steps,
+            ..replay.clone()
+        };
+        assert!(!verify(&last_undone, &levels).unwrap());
+
+        // Undo on the first board does nothing, restart starts over
+        let mut steps = vec![Step::Undo];
+        steps.extend(solution[..2].iter().copied());
+        steps.push(Step::Restart);
+        steps.extend(solution.iter().copied());
+        let restarted = Replay { steps, ..replay };
+        assert!(verify(&restarted, &levels).unwrap());
+    }
+
+    #[test]
+    fn verify_rejects_replays_it_cannot_play() {
+        let levels = Levels::default();
+        let replay = level14_replay(&levels);
+        let error = |replay: &Replay| {
+            verify(replay, &levels)
+                .unwrap_err()
+                .downcast::<ReplayError>()
+                .unwrap()
+        };
+
+        let changed = Replay {
+            level_hash: replay.level_hash ^ 1,
+            ..replay.clone()
+        };
+        assert!(matches!(error(&changed), ReplayError::LevelChanged(14)));
+
+        let unknown = Replay {
+            level: levels.levels.len() + 1,
+            ..replay.clone()
+        };
+        assert!(matches!(error(&unknown), ReplayError::UnknownLevel(_)));
+
+        let no_duck = Replay {
+            steps: moves(&[((1, 1), Direction::Right), ((1, 1), Direction::Down)]),
+            ..replay
+        };
+        assert!(matches!(error(&no_duck), ReplayError::NoDuck(2, (1, 1))));
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::BTreeMap;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::*;
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_ron::<SaveData>(SAVE_NAME).unwrap_or_default())
+            .add_systems(
+                Update,
+                write_save_data.run_if(changed_after_added::<SaveData>),
+            );
+    }
+}
+
+const SAVE_NAME: &str = "save.ron";
+
+// Kept between sessions, see write_storage
+#[derive(Resource, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct SaveData {
+    // Level index -> hints used on the level
+    pub hints_used: BTreeMap<usize, u32>,
+}
+
+// A file next to the game natively, an item of the local storage in the browser
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_storage(name: &str) -> Option<String> {
+    std::fs::read_to_string(name).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_storage(name: &str, text: &str) -> anyhow::Result<()> {
+    std::fs::write(name, text)?;
+    Ok(())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn storage_key(name: &str) -> String {
+    format!("quack_on_ice/{}", name)
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn read_storage(name: &str) -> Option<String> {
+    local_storage()?.get_item(&storage_key(name)).ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn write_storage(name: &str, text: &str) -> anyhow::Result<()> {
+    local_storage()
+        .ok_or_else(|| anyhow::anyhow!("No local storage"))?
+        .set_item(&storage_key(name), text)
+        .map_err(|_| anyhow::anyhow!("Fail to write the local storage"))
+}
+
+// A value kept in its own file, None when the file is missing or broken
+// The caller falls back to its default, a broken file is told in the log
+pub fn load_ron<T: DeserializeOwned>(name: &str) -> Option<T> {
+    let text = read_storage(name)?;
+    match ron::from_str(&text) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            warn!("Fail to read {}: {}", name, err);
+            None
+        }
+    }
+}
+
+pub fn save_ron<T: Serialize>(name: &str, value: &T) {
+    let result = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+        .map_err(anyhow::Error::from)
+        .and_then(|text| write_storage(name, &text));
+    if let Err(err) = result {
+        error!("Fail to save {}: {}", name, err);
+    }
+}
+
+// Run condition for the writers, the value loaded at startup isn't written back
+pub fn changed_after_added<T: Resource>(resource: Res<T>) -> bool {
+    resource.is_changed() && !resource.is_added()
+}
+
+fn write_save_data(save_data: Res<SaveData>) {
+    save_ron(SAVE_NAME, &*save_data);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Resource, Default)]
+    struct Value(u32);
+
+    #[derive(Resource, Default)]
+    struct Writes(u32);
+
+    fn count_writes(mut writes: ResMut<Writes>) {
+        writes.0 += 1;
+    }
+
+    #[test]
+    fn writers_skip_the_loaded_value() {
+        let mut app = App::new();
+        app.init_resource::<Value>()
+            .init_resource::<Writes>()
+            .add_systems(Update, count_writes.run_if(changed_after_added::<Value>));
+
+        app.update();
+        app.update();
+        assert_eq!(app.world().resource::<Writes>().0, 0);
+
+        app.world_mut().resource_mut::<Value>().0 = 1;
+        app.update();
+        app.update();
+        assert_eq!(app.world().resource::<Writes>().0, 1);
+    }
+}
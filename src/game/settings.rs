@@ -0,0 +1,457 @@
+use super::{
+    accessibility::{Accessibility, AccessibilityOption},
+    controls::{Action, Actions, Bindings, ControlsMenu, KeyBinding},
+    particles::Effects,
+    theme::{BackgroundRole, TextRole, Theme, ThemeRole},
+    *,
+};
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                toggle_controls_menu,
+                // Before the buttons, so the key that starts a rebinding isn't taken as the new key
+                capture_binding,
+                panel_button_interaction,
+                highlight_focused_button.run_if(resource_changed::<ControlsMenu>),
+                update_panel_texts.run_if(
+                    resource_changed::<Bindings>
+                        .or_else(resource_changed::<ControlsMenu>)
+                        .or_else(resource_changed::<Theme>)
+                        .or_else(resource_changed::<Accessibility>)
+                        .or_else(resource_changed::<Effects>),
+                ),
+            )
+                .chain(),
+        );
+    }
+}
+
+#[derive(Component)]
+struct ControlsPanel;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+enum PanelButton {
+    // Click it, then press the new key of the action
+    Binding(Action),
+    ResetBindings,
+    // Switches to the next palette
+    Theme,
+    Accessibility(AccessibilityOption),
+    Particles,
+}
+
+// Under the bindings, in one row
+const OPTION_BUTTONS: [PanelButton; 3] = [
+    PanelButton::ResetBindings,
+    PanelButton::Theme,
+    PanelButton::Particles,
+];
+
+// Every button in the order they are laid out, the focus moves through them
+fn panel_buttons() -> Vec<PanelButton> {
+    Action::ALL
+        .into_iter()
+        .map(PanelButton::Binding)
+        .chain(OPTION_BUTTONS)
+        .chain(
+            AccessibilityOption::ALL
+                .into_iter()
+                .map(PanelButton::Accessibility),
+        )
+        .collect()
+}
+
+// The text of the button, shows what the button is set to
+#[derive(Component)]
+struct PanelText(PanelButton);
+
+fn toggle_controls_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    actions: Res<Actions>,
+    panel_query: Query<Entity, With<ControlsPanel>>,
+    mut controls_menu: ResMut<ControlsMenu>,
+    theme: Res<Theme>,
+) {
+    let close = controls_menu.open && actions.menu_pressed(Action::Back);
+    if !actions.just_pressed(Action::Controls) && !close {
+        return;
+    }
+    if controls_menu.open {
+        for entity in panel_query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        *controls_menu = ControlsMenu::default();
+        return;
+    }
+    controls_menu.open = true;
+
+    let text_style = TextStyle {
+        font: asset_server.load("fonts/NotJamChunky8.ttf"),
+        font_size: 14.0,
+        color: theme.text,
+    };
+    // Wraps when the options don't fit on one line
+    let row = || NodeBundle {
+        style: Style {
+            margin: UiRect::top(Val::Px(6.0)),
+            max_width: Val::Px(900.0),
+            flex_wrap: FlexWrap::Wrap,
+            justify_content: JustifyContent::Center,
+            column_gap: Val::Px(6.0),
+            row_gap: Val::Px(6.0),
+            ..default()
+        },
+        ..default()
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(4.0),
+                    ..default()
+                },
+                background_color: theme.background.with_alpha(0.95).into(),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            ControlsPanel,
+            BackgroundRole(ThemeRole::Background),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "Choose an action, then press its new key. Esc to close",
+                    TextStyle {
+                        color: theme.accent,
+                        ..text_style.clone()
+                    },
+                ),
+                TextRole(ThemeRole::Accent),
+            ));
+            for action in Action::ALL {
+                spawn_panel_button(
+                    parent,
+                    PanelButton::Binding(action),
+                    Val::Px(460.0),
+                    &text_style,
+                    &theme,
+                );
+            }
+            parent.spawn(row()).with_children(|parent| {
+                for button in OPTION_BUTTONS {
+                    spawn_panel_button(parent, button, Val::Auto, &text_style, &theme);
+                }
+            });
+            parent.spawn(row()).with_children(|parent| {
+                for option in AccessibilityOption::ALL {
+                    spawn_panel_button(
+                        parent,
+                        PanelButton::Accessibility(option),
+                        Val::Auto,
+                        &text_style,
+                        &theme,
+                    );
+                }
+            });
+        });
+}
+
+// The text is filled in by update_panel_texts
+fn spawn_panel_button(
+    parent: &mut ChildBuilder,
+    button: PanelButton,
+    width: Val,
+    text_style: &TextStyle,
+    theme: &Theme,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    width,
+                    padding: UiRect::axes(Val::Px(10.0), Val::Px(4.0)),
+                    ..default()
+                },
+                background_color: theme.secondary.into(),
+                ..default()
+            },
+            button,
+            BackgroundRole(ThemeRole::Secondary),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section("", text_style.clone()),
+                PanelText(button),
+                TextRole(ThemeRole::Text),
+            ));
+        });
+}
+
+// The mouse, or the moves and Confirm of the keyboard and the gamepad
+fn panel_button_interaction(
+    interaction_query: Query<(&Interaction, &PanelButton), Changed<Interaction>>,
+    actions: Res<Actions>,
+    mut controls_menu: ResMut<ControlsMenu>,
+    mut bindings: ResMut<Bindings>,
+    mut theme: ResMut<Theme>,
+    mut accessibility: ResMut<Accessibility>,
+    mut effects: ResMut<Effects>,
+) {
+    if !controls_menu.open || controls_menu.rebinding.is_some() {
+        return;
+    }
+    let buttons = panel_buttons();
+    let mut focus = controls_menu.focus;
+    let mut pressed = None;
+    for (interaction, &button) in &interaction_query {
+        let index = buttons.iter().position(|&other| other == button);
+        match *interaction {
+            Interaction::Pressed => {
+                focus = index.unwrap_or(focus);
+                pressed = Some(button);
+            }
+            Interaction::Hovered => focus = index.unwrap_or(focus),
+            Interaction::None => (),
+        }
+    }
+    // Up and left go back through the buttons, down and right go on, both wrap around
+    focus = match actions.menu_direction() {
+        utils::Direction::Up | utils::Direction::Left => {
+            (focus + buttons.len() - 1) % buttons.len()
+        }
+        utils::Direction::Down | utils::Direction::Right => (focus + 1) % buttons.len(),
+        utils::Direction::None => focus,
+    };
+    if actions.menu_pressed(Action::Confirm) {
+        pressed = buttons.get(focus).copied();
+    }
+    if focus != controls_menu.focus {
+        controls_menu.focus = focus;
+    }
+    let Some(button) = pressed else {
+        return;
+    };
+    match button {
+        PanelButton::Binding(action) => controls_menu.rebinding = Some(action),
+        PanelButton::ResetBindings => *bindings = Bindings::default(),
+        PanelButton::Theme => *theme = Theme::new(theme.palette.next()),
+        PanelButton::Accessibility(option) => option.toggle(&mut accessibility),
+        PanelButton::Particles => effects.particles = !effects.particles,
+    }
+}
+
+// The focused button takes the hover color, the mouse moves the focus too
+fn highlight_focused_button(
+    mut button_query: Query<(&PanelButton, &mut BackgroundRole)>,
+    controls_menu: Res<ControlsMenu>,
+) {
+    let focused = panel_buttons().get(controls_menu.focus).copied();
+    for (&button, mut background_role) in button_query.iter_mut() {
+        let role = if Some(button) == focused {
+            BackgroundRole(ThemeRole::Accent)
+        } else {
+            BackgroundRole(ThemeRole::Secondary)
+        };
+        background_role.set_if_neq(role);
+    }
+}
+
+// The next key pressed is the new main key of the action, Esc keeps the old one
+// A gamepad has no keys, its back button keeps the old one too
+fn capture_binding(
+    input: Res<ButtonInput<KeyCode>>,
+    gamepad_input: Res<ButtonInput<GamepadButton>>,
+    mut controls_menu: ResMut<ControlsMenu>,
+    mut bindings: ResMut<Bindings>,
+) {
+    let Some(action) = controls_menu.rebinding else {
+        return;
+    };
+    if bindings.gamepad_just_pressed(Action::Back, &gamepad_input) {
+        controls_menu.rebinding = None;
+        controls_menu.refused = None;
+        return;
+    }
+    let shift = input.pressed(KeyCode::ShiftLeft) || input.pressed(KeyCode::ShiftRight);
+    let Some(&key) = input
+        .get_just_pressed()
+        .find(|&&key| key != KeyCode::ShiftLeft && key != KeyCode::ShiftRight)
+    else {
+        return;
+    };
+    let binding = KeyBinding { key, shift };
+    // Every action keeps a key, so the menu can always be opened again
+    if key != KeyCode::Escape {
+        if let Err(other) = bindings.rebind(action, binding) {
+            controls_menu.refused = Some((binding, other));
+            return;
+        }
+    }
+    controls_menu.rebinding = None;
+    controls_menu.refused = None;
+}
+
+fn update_panel_texts(
+    bindings: Res<Bindings>,
+    controls_menu: Res<ControlsMenu>,
+    theme: Res<Theme>,
+    accessibility: Res<Accessibility>,
+    effects: Res<Effects>,
+    mut text_query: Query<(&mut Text, &PanelText)>,
+) {
+    for (mut text, PanelText(button)) in text_query.iter_mut() {
+        text.sections[0].value = match *button {
+            PanelButton::Binding(action) if controls_menu.rebinding == Some(action) => {
+                match controls_menu.refused {
+                    Some((binding, other)) => format!(
+                        "{}: {} is the only key of {}, press another...",
+                        action.label(),
+                        binding.name(),
+                        other.label()
+                    ),
+                    None => format!("{}: press a key...", action.label()),
+                }
+            }
+            PanelButton::Binding(action) => format!(
+                "{}: {}",
+                action.label(),
+                bindings
+                    .keys(action)
+                    .iter()
+                    .map(KeyBinding::name)
+                    .collect::<Vec<String>>()
+                    .join(" / ")
+            ),
+            PanelButton::ResetBindings => "Reset to defaults".to_string(),
+            PanelButton::Theme => format!("Theme: {}", theme.palette.name()),
+            PanelButton::Accessibility(option) => format!(
+                "{}: {}",
+                option.label(),
+                if option.is_on(&accessibility) {
+                    "on"
+                } else {
+                    "off"
+                }
+            ),
+            PanelButton::Particles => {
+                format!(
+                    "Particles: {}",
+                    if effects.particles { "on" } else { "off" }
+                )
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{controls::read_actions, theme::Palette};
+
+    fn menu_app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Font>()
+            .init_resource::<ButtonInput<KeyCode>>()
+            .init_resource::<ButtonInput<GamepadButton>>()
+            .init_resource::<Gamepads>()
+            .init_resource::<Axis<GamepadAxis>>()
+            .init_resource::<Bindings>()
+            .init_resource::<Actions>()
+            .init_resource::<ControlsMenu>()
+            .init_resource::<Accessibility>()
+            .init_resource::<Effects>()
+            .insert_resource(Theme::new(Palette::Dark))
+            .add_systems(PreUpdate, read_actions)
+            .add_plugins(Plugin);
+        app
+    }
+
+    // Release everything pressed before, then press the button for one frame
+    fn press(app: &mut App, button_type: GamepadButtonType) {
+        let mut input = app.world_mut().resource_mut::<ButtonInput<GamepadButton>>();
+        input.release_all();
+        input.clear();
+        input.press(GamepadButton::new(Gamepad::new(0), button_type));
+        app.update();
+    }
+
+    fn focused_button(app: &mut App) -> Option<PanelButton> {
+        let focus = app.world().resource::<ControlsMenu>().focus;
+        let mut button_query = app.world_mut().query::<(&PanelButton, &BackgroundRole)>();
+        let highlighted: Vec<PanelButton> = button_query
+            .iter(app.world())
+            .filter(|(_, role)| **role == BackgroundRole(ThemeRole::Accent))
+            .map(|(&button, _)| button)
+            .collect();
+        assert_eq!(highlighted.len(), 1);
+        assert_eq!(panel_buttons().get(focus), highlighted.first());
+        highlighted.first().copied()
+    }
+
+    #[test]
+    fn gamepad_drives_the_controls_menu() {
+        let mut app = menu_app();
+        app.update();
+
+        press(&mut app, GamepadButtonType::Select);
+        assert!(app.world().resource::<ControlsMenu>().open);
+        assert_eq!(
+            focused_button(&mut app),
+            Some(PanelButton::Binding(Action::MoveUp))
+        );
+
+        // The D-pad moves the focus and stays out of the game
+        press(&mut app, GamepadButtonType::DPadDown);
+        assert!(!app
+            .world()
+            .resource::<Actions>()
+            .just_pressed(Action::MoveDown));
+        assert_eq!(
+            focused_button(&mut app),
+            Some(PanelButton::Binding(Action::MoveDown))
+        );
+        press(&mut app, GamepadButtonType::DPadUp);
+        press(&mut app, GamepadButtonType::DPadUp);
+        assert_eq!(
+            focused_button(&mut app),
+            Some(PanelButton::Accessibility(
+                *AccessibilityOption::ALL.last().unwrap()
+            ))
+        );
+        press(&mut app, GamepadButtonType::DPadDown);
+        press(&mut app, GamepadButtonType::DPadDown);
+
+        // South presses the focused button, East backs out of the rebinding and then the menu
+        press(&mut app, GamepadButtonType::South);
+        assert_eq!(
+            app.world().resource::<ControlsMenu>().rebinding,
+            Some(Action::MoveDown)
+        );
+        press(&mut app, GamepadButtonType::East);
+        let controls_menu = app.world().resource::<ControlsMenu>();
+        assert!(controls_menu.open);
+        assert_eq!(controls_menu.rebinding, None);
+        assert_eq!(
+            app.world().resource::<Bindings>().keys(Action::MoveDown),
+            Bindings::default().keys(Action::MoveDown)
+        );
+
+        press(&mut app, GamepadButtonType::East);
+        assert!(!app.world().resource::<ControlsMenu>().open);
+        let mut panel_query = app.world_mut().query::<&ControlsPanel>();
+        assert_eq!(panel_query.iter(app.world()).count(), 0);
+    }
+}
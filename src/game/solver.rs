@@ -0,0 +1,526 @@
+use std::collections::VecDeque;
+
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task};
+use bevy::utils::HashSet;
+
+use super::{
+    goal::{goals_lost, goals_met, is_duck, BoardState},
+    level::{self, Level, LevelConfig, LevelStack, TotalBreadCount},
+    player::{duck_from_symbol, slip},
+    switch::update_switches,
+    *,
+};
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Stuck>().add_systems(
+            Update,
+            detect_stuck
+                .after(level::update_level)
+                .run_if(in_state(GameStates::Next)),
+        );
+    }
+}
+
+// How many boards the solver looks at before giving up
+pub const STUCK_SOLVER_BUDGET: usize = 5_000;
+// The player asked for a solution, so the solver may look further than when checking for stuck boards
+pub const SOLUTION_SOLVER_BUDGET: usize = 50_000;
+
+// A push of the duck on the tile, on the board the move is played on
+// The position is enough to tell which duck: a tile holds one duck at a time, and a move is only
+// played on the board it was made for, the board the solver started from or the level start for
+// a replay. Its DuckId is the one DuckRecordStack has at that position on that board
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Move {
+    pub position: (usize, usize),
+    pub direction: utils::Direction,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum Verdict {
+    // The shortest way to win
+    Solvable(Vec<Move>),
+    Unsolvable,
+    // Ran out of budget
+    Unknown,
+}
+
+// One move the way player_movement and update_level make it, None if nothing would change
+pub fn apply_move(
+    level: &[Vec<char>],
+    config: &LevelConfig,
+    duck_move: Move,
+) -> Option<Vec<Vec<char>>> {
+    let (row, col) = duck_move.position;
+    let mut duck = duck_from_symbol(level[row][col], duck_move.position)?;
+    if !duck.can_move {
+        return None;
+    }
+    let mut next = level.to_vec();
+    slip(&mut duck, duck_move.direction, &mut next);
+    update_switches(&mut next, config);
+    (next.as_slice() != level).then_some(next)
+}
+
+struct Node {
+    level: Vec<Vec<char>>,
+    parent: Option<(usize, Move)>,
+    depth: usize,
+}
+
+// Breadth first search from the board, so the moves found are the fewest
+// It can stop after any number of boards and carry on later
+struct Search {
+    config: LevelConfig,
+    moves_made: usize,
+    total_bread: i32,
+    budget: usize,
+    won_already: bool,
+    nodes: Vec<Node>,
+    seen: HashSet<Vec<Vec<char>>>,
+    queue: VecDeque<usize>,
+    out_of_budget: bool,
+}
+
+impl Search {
+    fn new(
+        level: &[Vec<char>],
+        config: &LevelConfig,
+        moves_made: usize,
+        total_bread: i32,
+        budget: usize,
+    ) -> Self {
+        let won_already = goals_met(&BoardState {
+            level,
+            config,
+            moves: moves_made,
+            total_bread,
+        });
+        Search {
+            config: config.clone(),
+            moves_made,
+            total_bread,
+            budget,
+            won_already,
+            nodes: vec![Node {
+                level: level.to_vec(),
+                parent: None,
+                depth: 0,
+            }],
+            seen: [level.to_vec()].into_iter().collect(),
+            queue: VecDeque::from([0]),
+            out_of_budget: false,
+        }
+    }
+
+    // Looks at the moves from up to `boards` boards, Some once the verdict is in
+    fn step(&mut self, boards: usize) -> Option<Verdict> {
+        if self.won_already {
+            return Some(Verdict::Solvable(Vec::new()));
+        }
+        for _ in 0..boards {
+            let Some(index) = self.queue.pop_front() else {
+                return Some(if self.out_of_budget {
+                    Verdict::Unknown
+                } else {
+                    Verdict::Unsolvable
+                });
+            };
+            if let Some(moves) = self.expand(index) {
+                return Some(Verdict::Solvable(moves));
+            }
+        }
+        None
+    }
+
+    #[cfg(any(test, not(target_arch = "wasm32")))]
+    fn run(mut self) -> Verdict {
+        loop {
+            if let Some(verdict) = self.step(usize::MAX) {
+                return verdict;
+            }
+        }
+    }
+
+    // Queues the boards one move away, Some with the moves when one of them wins
+    fn expand(&mut self, index: usize) -> Option<Vec<Move>> {
+        let current = self.nodes[index].level.clone();
+        let depth = self.nodes[index].depth + 1;
+        for (row_index, row) in current.iter().enumerate() {
+            for (col_index, &ch) in row.iter().enumerate() {
+                if !is_duck(ch) {
+                    continue;
+                }
+                for direction in utils::Direction::ALL {
+                    let duck_move = Move {
+                        position: (row_index, col_index),
+                        direction,
+                    };
+                    let Some(next) = apply_move(&current, &self.config, duck_move) else {
+                        continue;
+                    };
+                    if self.seen.contains(&next) {
+                        continue;
+                    }
+                    let board = BoardState {
+                        level: &next,
+                        config: &self.config,
+                        moves: self.moves_made + depth,
+                        total_bread: self.total_bread,
+                    };
+                    if goals_met(&board) {
+                        let mut moves = vec![duck_move];
+                        let mut parent = index;
+                        while let Some((grandparent, parent_move)) = self.nodes[parent].parent {
+                            moves.push(parent_move);
+                            parent = grandparent;
+                        }
+                        moves.reverse();
+                        return Some(moves);
+                    }
+                    if goals_lost(&board) {
+                        self.seen.insert(next);
+                        continue;
+                    }
+                    if self.nodes.len() >= self.budget {
+                        self.out_of_budget = true;
+                        continue;
+                    }
+                    self.seen.insert(next.clone());
+                    self.nodes.push(Node {
+                        level: next,
+                        parent: Some((index, duck_move)),
+                        depth,
+                    });
+                    self.queue.push_back(self.nodes.len() - 1);
+                }
+            }
+        }
+        None
+    }
+}
+
+// Solve on the spot, it holds up the frame so the game goes through SolveTask
+#[cfg(test)]
+pub fn solve(
+    level: &[Vec<char>],
+    config: &LevelConfig,
+    moves_made: usize,
+    total_bread: i32,
+    budget: usize,
+) -> Verdict {
+    Search::new(level, config, moves_made, total_bread, budget).run()
+}
+
+// Boards searched per frame on the web
+#[cfg(target_arch = "wasm32")]
+const BOARDS_PER_FRAME: usize = 200;
+
+// A solve that doesn't hold up the frames, dropping it cancels it
+pub struct SolveTask {
+    #[cfg(not(target_arch = "wasm32"))]
+    task: Task<Verdict>,
+    // Tasks share the main thread on the web, so the search goes a few boards a frame instead
+    #[cfg(target_arch = "wasm32")]
+    search: Search,
+}
+
+impl SolveTask {
+    pub fn spawn(
+        level: &[Vec<char>],
+        config: &LevelConfig,
+        moves_made: usize,
+        total_bread: i32,
+        budget: usize,
+    ) -> Self {
+        Self::start(Search::new(level, config, moves_made, total_bread, budget))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn start(search: Search) -> Self {
+        SolveTask {
+            task: AsyncComputeTaskPool::get().spawn(async move { search.run() }),
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn start(search: Search) -> Self {
+        SolveTask { search }
+    }
+
+    // Some once the verdict is in, drop the task then
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn poll(&mut self) -> Option<Verdict> {
+        block_on(future::poll_once(&mut self.task))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn poll(&mut self) -> Option<Verdict> {
+        self.search.step(BOARDS_PER_FRAME)
+    }
+}
+
+// No way left to win the level from the current board
+#[derive(Resource, Default)]
+pub struct Stuck(pub bool);
+
+// Obvious dead ends are told right away, the solver looks for the subtle ones in the background
+fn detect_stuck(
+    level: Res<Level>,
+    config: Res<LevelConfig>,
+    level_stack: Res<LevelStack>,
+    total_bread_count: Res<TotalBreadCount>,
+    mut stuck: ResMut<Stuck>,
+    mut solving: Local<Option<SolveTask>>,
+) {
+    if level.is_changed() || level_stack.is_changed() {
+        // The board the solve was started for is gone
+        *solving = None;
+        // The level isn't loaded yet
+        if config.goals.is_empty() {
+            return;
+        }
+        // Wait for the board to settle: a pushed duck is on the board before update_level opens
+        // the gates and stacks it, and a new level is stacked before the board and its config
+        // are put in
+        if level_stack.0.peek() != Some(&level.0) {
+            return;
+        }
+        let moves = level_stack.0.size().saturating_sub(1);
+        let board = BoardState {
+            level: &level.0,
+            config: &config,
+            moves,
+            total_bread: total_bread_count.0,
+        };
+        if goals_met(&board) {
+            stuck.set_if_neq(Stuck(false));
+        } else if goals_lost(&board) {
+            stuck.set_if_neq(Stuck(true));
+        } else {
+            stuck.set_if_neq(Stuck(false));
+            *solving = Some(SolveTask::spawn(
+                &level.0,
+                &config,
+                moves,
+                total_bread_count.0,
+                STUCK_SOLVER_BUDGET,
+            ));
+        }
+    }
+    let Some(verdict) = solving.as_mut().and_then(SolveTask::poll) else {
+        return;
+    };
+    *solving = None;
+    stuck.set_if_neq(Stuck(verdict == Verdict::Unsolvable));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::game::{
+        goal::{AllBreadEaten, MoveLimit, WinCondition},
+        level::{load_level, Levels},
+    };
+
+    fn bread_config() -> LevelConfig {
+        LevelConfig {
+            goals: vec![Arc::new(AllBreadEaten) as Arc<dyn WinCondition>],
+            ..default()
+        }
+    }
+
+    // The fewest moves that win the shipped level
+    fn solve_level(level_index: usize) -> Option<usize> {
+        let (level, config) = load_level(level_index, &Levels::default()).unwrap();
+        let total_bread = level.0.iter().flatten().filter(|&&ch| ch == 'B').count() as i32;
+        match solve(&level.0, &config, 0, total_bread, SOLUTION_SOLVER_BUDGET) {
+            Verdict::Solvable(moves) => Some(moves.len()),
+            _ => None,
+        }
+    }
+
+    fn board_state<'a>(
+        level: &'a [Vec<char>],
+        config: &'a LevelConfig,
+        moves: usize,
+    ) -> BoardState<'a> {
+        BoardState {
+            level,
+            config,
+            moves,
+            total_bread: 1,
+        }
+    }
+
+    #[test]
+    fn apply_move_slides_and_eats() {
+        let level = board(&["@@@@@", "@D#B@", "@@@@@"]);
+        let next = apply_move(
+            &level,
+            &bread_config(),
+            Move {
+                position: (1, 1),
+                direction: utils::Direction::Right,
+            },
+        );
+        assert_eq!(next, Some(board(&["@@@@@", "@##Q@", "@@@@@"])));
+    }
+
+    #[test]
+    fn apply_move_without_change_is_none() {
+        let level = board(&["@@@@@", "@D#B@", "@@@@@"]);
+        let into_wall = Move {
+            position: (1, 1),
+            direction: utils::Direction::Up,
+        };
+        assert_eq!(apply_move(&level, &bread_config(), into_wall), None);
+        let no_duck = Move {
+            position: (1, 2),
+            direction: utils::Direction::Right,
+        };
+        assert_eq!(apply_move(&level, &bread_config(), no_duck), None);
+    }
+
+    #[test]
+    fn apply_move_ignores_sick_ducks() {
+        let level = board(&["@@@@", "@S#@", "@@@@"]);
+        let duck_move = Move {
+            position: (1, 1),
+            direction: utils::Direction::Right,
+        };
+        assert_eq!(apply_move(&level, &bread_config(), duck_move), None);
+    }
+
+    #[test]
+    fn solve_finds_the_shortest_solution() {
+        let level = board(&["@@@@@", "@D#@@", "@@#B@", "@@@@@"]);
+        let verdict = solve(&level, &bread_config(), 0, 1, STUCK_SOLVER_BUDGET);
+        let moves = [
+            ((1, 1), utils::Direction::Right),
+            ((1, 2), utils::Direction::Down),
+            ((2, 2), utils::Direction::Right),
+        ]
+        .map(|(position, direction)| Move {
+            position,
+            direction,
+        });
+        assert_eq!(verdict, Verdict::Solvable(moves.to_vec()));
+    }
+
+    #[test]
+    fn solve_won_board_needs_no_moves() {
+        let level = board(&["@@@@", "@Q#@", "@@@@"]);
+        let verdict = solve(&level, &bread_config(), 0, 1, STUCK_SOLVER_BUDGET);
+        assert_eq!(verdict, Verdict::Solvable(Vec::new()));
+    }
+
+    #[test]
+    fn solve_unsolvable_board() {
+        // The only duck is stuffed and stuffed ducks can't eat
+        let level = board(&["@@@@@", "@Q#B@", "@@@@@"]);
+        let verdict = solve(&level, &bread_config(), 0, 1, STUCK_SOLVER_BUDGET);
+        assert_eq!(verdict, Verdict::Unsolvable);
+    }
+
+    #[test]
+    fn solve_over_budget_is_unknown() {
+        let level = board(&["@@@@@", "@D#@@", "@@#B@", "@@@@@"]);
+        let verdict = solve(&level, &bread_config(), 0, 1, 1);
+        assert_eq!(verdict, Verdict::Unknown);
+    }
+
+    #[test]
+    fn solve_respects_a_move_limit() {
+        let level = board(&["@@@@@", "@D#@@", "@@#B@", "@@@@@"]);
+        let mut config = bread_config();
+        config
+            .goals
+            .push(Arc::new(MoveLimit(2)) as Arc<dyn WinCondition>);
+        let verdict = solve(&level, &config, 0, 1, STUCK_SOLVER_BUDGET);
+        assert_eq!(verdict, Verdict::Unsolvable);
+    }
+
+    #[test]
+    fn goals_lost_without_hungry_ducks() {
+        let config = bread_config();
+        let hungry = board(&["@@@@@", "@D#B@", "@@@@@"]);
+        assert!(!goals_lost(&board_state(&hungry, &config, 0)));
+        let stuffed = board(&["@@@@@", "@Q#B@", "@@@@@"]);
+        assert!(goals_lost(&board_state(&stuffed, &config, 0)));
+    }
+
+    #[test]
+    fn goals_lost_past_the_move_limit() {
+        let mut config = bread_config();
+        config
+            .goals
+            .push(Arc::new(MoveLimit(2)) as Arc<dyn WinCondition>);
+        let level = board(&["@@@@@", "@D#B@", "@@@@@"]);
+        assert!(!goals_lost(&board_state(&level, &config, 2)));
+        assert!(goals_lost(&board_state(&level, &config, 3)));
+    }
+
+    #[test]
+    fn search_can_stop_and_carry_on() {
+        let level = board(&["@@@@@", "@D#@@", "@@#B@", "@@@@@"]);
+        let mut search = Search::new(&level, &bread_config(), 0, 1, STUCK_SOLVER_BUDGET);
+        assert_eq!(search.step(1), None);
+        assert!(matches!(search.run(), Verdict::Solvable(moves) if moves.len() == 3));
+    }
+
+    #[test]
+    fn portal_level_is_solvable() {
+        assert_eq!(solve_level(14), Some(4));
+    }
+
+    #[test]
+    fn arrow_level_is_solvable() {
+        assert_eq!(solve_level(15), Some(4));
+    }
+
+    #[test]
+    fn gate_level_is_solvable() {
+        assert_eq!(solve_level(16), Some(4));
+    }
+
+    #[test]
+    fn moldy_bread_level_is_solvable() {
+        assert_eq!(solve_level(17), Some(3));
+    }
+
+    #[test]
+    fn nest_level_is_solvable() {
+        assert_eq!(solve_level(18), Some(2));
+    }
+
+    #[test]
+    fn stuck_waits_for_the_board_to_settle() {
+        let start = board(&["@@@@@@", "@D#B#@", "@@@@@@"]);
+        // Bread left and no hungry duck
+        let lost = board(&["@@@@@@", "@Q#B#@", "@@@@@@"]);
+        let mut level_stack = LevelStack::default();
+        level_stack.0.push(start);
+        let mut app = App::new();
+        app.insert_resource(Level(lost.clone()))
+            .insert_resource(bread_config())
+            .insert_resource(level_stack)
+            .insert_resource(TotalBreadCount(1))
+            .init_resource::<Stuck>()
+            .add_systems(Update, detect_stuck);
+
+        // The board changed, update_level hasn't stacked it yet
+        app.update();
+        assert!(!app.world().resource::<Stuck>().0);
+
+        app.world_mut().resource_mut::<LevelStack>().0.push(lost);
+        app.update();
+        assert!(app.world().resource::<Stuck>().0);
+    }
+}
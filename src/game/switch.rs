@@ -0,0 +1,165 @@
+use super::{
+    audio::PlaySFX,
+    level::{LevelConfig, SymbolType::*},
+    *,
+};
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BoardEvent>()
+            .add_systems(Update, play_board_sounds.run_if(in_state(GameStates::Next)));
+    }
+}
+
+// Sent when the board changes after a move or an undo
+#[derive(Event, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BoardEvent {
+    PlatePressed((usize, usize)),
+    PlateReleased((usize, usize)),
+    GateOpened((usize, usize)),
+    GateClosed((usize, usize)),
+}
+
+// Any duck standing on the plate, sick ones too
+fn is_pressed(level: &[Vec<char>], plate: (usize, usize)) -> bool {
+    [
+        DuckOnIce.get_symbol(),
+        StuffedDuckOnIce.get_symbol(),
+        SickDuckOnIce.get_symbol(),
+    ]
+    .contains(&level[plate.0][plate.1])
+}
+
+// A gate stays open while any of its plates is pressed
+fn should_open(level: &[Vec<char>], config: &LevelConfig, gate: (usize, usize)) -> bool {
+    config
+        .links
+        .iter()
+        .any(|(plate, gates)| gates.contains(&gate) && is_pressed(level, *plate))
+}
+
+// Put back the plates ducks left and open or close the gates
+// A duck standing in an open gate keeps it open until it leaves
+pub fn update_switches(level: &mut [Vec<char>], config: &LevelConfig) {
+    config.restore_floor(level);
+    for &(row, col) in config.gates.iter() {
+        let symbol = level[row][col];
+        if [
+            Ice.get_symbol(),
+            OpenGate.get_symbol(),
+            ClosedGate.get_symbol(),
+        ]
+        .contains(&symbol)
+        {
+            let open = should_open(level, config, (row, col));
+            level[row][col] = if open {
+                OpenGate.get_symbol()
+            } else {
+                ClosedGate.get_symbol()
+            };
+        }
+    }
+}
+
+// What changed on the plates and gates between two boards
+pub fn board_events(
+    before: &[Vec<char>],
+    after: &[Vec<char>],
+    config: &LevelConfig,
+) -> Vec<BoardEvent> {
+    let mut events = Vec::new();
+    for &plate in config.plates.iter() {
+        match (is_pressed(before, plate), is_pressed(after, plate)) {
+            (false, true) => events.push(BoardEvent::PlatePressed(plate)),
+            (true, false) => events.push(BoardEvent::PlateReleased(plate)),
+            _ => (),
+        }
+    }
+    let is_open = |level: &[Vec<char>], gate: (usize, usize)| {
+        level[gate.0][gate.1] != ClosedGate.get_symbol()
+    };
+    for &gate in config.gates.iter() {
+        match (is_open(before, gate), is_open(after, gate)) {
+            (false, true) => events.push(BoardEvent::GateOpened(gate)),
+            (true, false) => events.push(BoardEvent::GateClosed(gate)),
+            _ => (),
+        }
+    }
+    events
+}
+
+fn play_board_sounds(
+    mut events: EventReader<BoardEvent>,
+    mut events_sfx: EventWriter<PlaySFX>,
+    audio_assets: Res<AudioAssets>,
+) {
+    for event in events.read() {
+        if let BoardEvent::GateOpened(_) | BoardEvent::GateClosed(_) = event {
+            events_sfx.send(PlaySFX {
+                source: audio_assets.ice_breaking.clone(),
+                volume: bevy::audio::Volume::new(0.2),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One plate at 0,2 and gates at 0,4 and 0,6, the plate only opens the first gate
+    fn config() -> LevelConfig {
+        LevelConfig {
+            plates: vec![(0, 2)],
+            gates: vec![(0, 4), (0, 6)],
+            links: vec![((0, 2), vec![(0, 4)])],
+            ..default()
+        }
+    }
+
+    fn switched(rows: &[&str]) -> Vec<Vec<char>> {
+        let mut level = board(rows);
+        update_switches(&mut level, &config());
+        level
+    }
+
+    #[test]
+    fn pressed_plate_opens_its_gates() {
+        assert_eq!(switched(&["@#D#=#=@"]), board(&["@#D#-#=@"]));
+        assert_eq!(switched(&["@#S#=#=@"]), board(&["@#S#-#=@"]));
+    }
+
+    #[test]
+    fn released_plate_comes_back_and_closes_its_gates() {
+        assert_eq!(switched(&["@D##-#=@"]), board(&["@D_#=#=@"]));
+    }
+
+    #[test]
+    fn duck_in_a_gate_keeps_it_open() {
+        assert_eq!(switched(&["@D_#D#=@"]), board(&["@D_#D#=@"]));
+        assert_eq!(switched(&["@D_##D=@"]), board(&["@D_#=D=@"]));
+    }
+
+    #[test]
+    fn board_events_list_what_changed() {
+        let before = board(&["@D_#=#=@"]);
+        let after = board(&["@#D#-#=@"]);
+        assert_eq!(
+            board_events(&before, &after, &config()),
+            vec![
+                BoardEvent::PlatePressed((0, 2)),
+                BoardEvent::GateOpened((0, 4))
+            ]
+        );
+        assert_eq!(
+            board_events(&after, &before, &config()),
+            vec![
+                BoardEvent::PlateReleased((0, 2)),
+                BoardEvent::GateClosed((0, 4))
+            ]
+        );
+        assert!(board_events(&before, &before, &config()).is_empty());
+    }
+}
@@ -0,0 +1,231 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    save::{changed_after_added, load_ron, save_ron},
+    *,
+};
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        let theme = Theme::new(load_ron(THEME_NAME).unwrap_or_default());
+        app.insert_resource(ClearColor(theme.background))
+            .insert_resource(theme)
+            .add_systems(
+                Update,
+                (
+                    apply_theme.run_if(resource_changed::<Theme>),
+                    write_palette.run_if(changed_after_added::<Theme>),
+                    paint_roles,
+                    tint_new_tiles,
+                    outline_tiles.run_if(in_state(GameStates::Next)),
+                ),
+            );
+    }
+}
+
+const THEME_NAME: &str = "theme.ron";
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug, Serialize, Deserialize)]
+pub enum Palette {
+    #[default]
+    Dark,
+    Light,
+    HighContrast,
+}
+
+impl Palette {
+    pub fn name(self) -> &'static str {
+        match self {
+            Palette::Dark => "Dark",
+            Palette::Light => "Light",
+            Palette::HighContrast => "High contrast",
+        }
+    }
+
+    // For the settings button, cycles through all palettes
+    pub fn next(self) -> Self {
+        match self {
+            Palette::Dark => Palette::Light,
+            Palette::Light => Palette::HighContrast,
+            Palette::HighContrast => Palette::Dark,
+        }
+    }
+
+    pub fn colors(self) -> Colors {
+        match self {
+            Palette::Dark => Colors {
+                background: DARK_MODE_BG_COLOR,
+                text: Color::WHITE,
+                accent: MY_ORANGE,
+                secondary: MY_BROWN,
+                hovered: Color::srgb(222.0 / 255.0 + 0.1, 112.0 / 255.0 + 0.1, 40.0 / 255.0 + 0.1),
+                pressed: Color::srgb(0.75, 0.75, 0.75),
+                tile: Color::WHITE,
+            },
+            Palette::Light => Colors {
+                background: Color::srgb(238.0 / 255.0, 234.0 / 255.0, 224.0 / 255.0),
+                text: Color::srgb(45.0 / 255.0, 47.0 / 255.0, 47.0 / 255.0),
+                accent: Color::srgb(190.0 / 255.0, 85.0 / 255.0, 20.0 / 255.0),
+                secondary: MY_BROWN,
+                hovered: MY_ORANGE,
+                pressed: Color::srgb(0.6, 0.6, 0.6),
+                tile: Color::srgb(0.95, 0.97, 1.0),
+            },
+            Palette::HighContrast => Colors {
+                background: Color::BLACK,
+                text: Color::WHITE,
+                accent: Color::srgb(1.0, 0.85, 0.0),
+                secondary: Color::srgb(0.2, 0.2, 0.2),
+                hovered: Color::srgb(1.0, 0.95, 0.4),
+                pressed: Color::srgb(0.5, 0.5, 0.5),
+                // Cyan ice and deep water on the black background
+                tile: Color::srgb(0.7, 1.0, 1.0),
+            },
+        }
+    }
+
+    // Every terrain tile in a frame, so the board reads without telling the colors apart
+    pub fn outlines_tiles(self) -> bool {
+        self == Palette::HighContrast
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Colors {
+    pub background: Color,
+    pub text: Color,
+    // Keys in the hints, titles, buttons of the HUD
+    pub accent: Color,
+    // Borders, buttons of the settings
+    pub secondary: Color,
+    pub hovered: Color,
+    pub pressed: Color,
+    // Multiplied with the terrain sprites
+    pub tile: Color,
+}
+
+impl Colors {
+    pub fn role(&self, role: ThemeRole) -> Color {
+        match role {
+            ThemeRole::Background => self.background,
+            ThemeRole::Text => self.text,
+            ThemeRole::Accent => self.accent,
+            ThemeRole::Secondary => self.secondary,
+            ThemeRole::Hovered => self.hovered,
+            ThemeRole::Pressed => self.pressed,
+        }
+    }
+}
+
+// What a ui color stands for, it takes the color the palette has for it
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ThemeRole {
+    Background,
+    Text,
+    Accent,
+    Secondary,
+    Hovered,
+    Pressed,
+}
+
+// Every section of the text, texts mixing roles are rebuilt when the theme changes instead
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TextRole(pub ThemeRole);
+
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BackgroundRole(pub ThemeRole);
+
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BorderRole(pub ThemeRole);
+
+// The palette in use, spawn ui with its colors
+#[derive(Resource, Deref)]
+pub struct Theme {
+    pub palette: Palette,
+    #[deref]
+    colors: Colors,
+}
+
+impl Theme {
+    pub fn new(palette: Palette) -> Self {
+        Theme {
+            palette,
+            colors: palette.colors(),
+        }
+    }
+}
+
+// Terrain tinted by the palette
+#[derive(Component)]
+pub struct Tile;
+
+// The clear color and the terrain, the ui goes by role in paint_roles
+fn apply_theme(
+    theme: Res<Theme>,
+    mut clear_color: ResMut<ClearColor>,
+    mut tile_query: Query<&mut Sprite, With<Tile>>,
+) {
+    clear_color.0 = theme.background;
+    for mut sprite in tile_query.iter_mut() {
+        sprite.color = theme.tile;
+    }
+}
+
+// Color the ui by role, everything when the theme changes, otherwise what got a new role
+// Buttons change role on hover and press, alpha is kept
+fn paint_roles(
+    theme: Res<Theme>,
+    mut text_query: Query<(&mut Text, Ref<TextRole>)>,
+    mut background_query: Query<(&mut BackgroundColor, Ref<BackgroundRole>)>,
+    mut border_query: Query<(&mut BorderColor, Ref<BorderRole>)>,
+) {
+    let repaint_all = theme.is_changed();
+    for (mut text, role) in text_query.iter_mut() {
+        if repaint_all || role.is_changed() {
+            let color = theme.role(role.0);
+            for section in text.sections.iter_mut() {
+                section.style.color = color.with_alpha(section.style.color.alpha());
+            }
+        }
+    }
+    for (mut background_color, role) in background_query.iter_mut() {
+        if repaint_all || role.is_changed() {
+            background_color.0 = theme.role(role.0).with_alpha(background_color.0.alpha());
+        }
+    }
+    for (mut border_color, role) in border_query.iter_mut() {
+        if repaint_all || role.is_changed() {
+            border_color.0 = theme.role(role.0).with_alpha(border_color.0.alpha());
+        }
+    }
+}
+
+fn tint_new_tiles(theme: Res<Theme>, mut tile_query: Query<&mut Sprite, Added<Tile>>) {
+    for mut sprite in tile_query.iter_mut() {
+        sprite.color = theme.tile;
+    }
+}
+
+// Swaps the atlas of the terrain for the outlined one and back
+fn outline_tiles(
+    theme: Res<Theme>,
+    image_assets: Res<ImageAssets>,
+    mut tile_query: Query<(&mut Handle<Image>, Ref<Tile>)>,
+) {
+    let atlas = if theme.palette.outlines_tiles() {
+        &image_assets.tile_atlas_outlined
+    } else {
+        &image_assets.tile_atlas
+    };
+    for (mut texture, tile) in tile_query.iter_mut() {
+        if theme.is_changed() || tile.is_added() {
+            *texture = atlas.clone();
+        }
+    }
+}
+
+fn write_palette(theme: Res<Theme>) {
+    save_ron(THEME_NAME, &theme.palette);
+}
@@ -0,0 +1,86 @@
+use super::{
+    autoplay::Autoplay,
+    controls::ControlsMenu,
+    cursor::{duck_at, select_duck, ArrowHint},
+    grid::GridIndex,
+    player::{MoveDuck, Player},
+    *,
+};
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, touch_input.run_if(in_state(GameStates::Next)));
+    }
+}
+
+// A finger moving less than this is a tap, more is a swipe
+const SWIPE_THRESHOLD: f32 = SPRITE_SIZE / 2.0;
+
+// Tap a duck to choose it, swipe to push the chosen duck
+// A swipe starting on a duck chooses and pushes that duck
+fn touch_input(
+    mut commands: Commands,
+    // query
+    camera_query: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    player_query: Query<Entity, With<Player>>,
+    arrow_hint_query: Query<Entity, (With<ArrowHint>, With<Parent>)>,
+    // resource
+    touches: Res<Touches>,
+    grid_index: Res<GridIndex>,
+    image_assets: Res<ImageAssets>,
+    autoplay: Res<Autoplay>,
+    controls_menu: Res<ControlsMenu>,
+    // event
+    mut events_move: EventWriter<MoveDuck>,
+) {
+    // The taps are for the controls menu
+    if controls_menu.open {
+        return;
+    }
+    let (camera, camera_transform) = camera_query.single();
+    for touch in touches.iter_just_released() {
+        let (Some(start), Some(end)) = (
+            camera.viewport_to_world_2d(camera_transform, touch.start_position()),
+            camera.viewport_to_world_2d(camera_transform, touch.position()),
+        ) else {
+            continue;
+        };
+        let touched_duck = duck_at(start, &grid_index);
+        let player = player_query.get_single().ok();
+        if let Some(entity) = touched_duck {
+            if Some(entity) != player {
+                select_duck(
+                    &mut commands,
+                    entity,
+                    &player_query,
+                    &arrow_hint_query,
+                    &image_assets,
+                );
+            }
+        }
+
+        let swipe = end - start;
+        if swipe.length() < SWIPE_THRESHOLD || autoplay.is_active() {
+            continue;
+        }
+        let direction = if swipe.x.abs() > swipe.y.abs() {
+            if swipe.x > 0.0 {
+                utils::Direction::Right
+            } else {
+                utils::Direction::Left
+            }
+        } else if swipe.y > 0.0 {
+            utils::Direction::Up
+        } else {
+            utils::Direction::Down
+        };
+        if let Some(entity) = touched_duck.or(player) {
+            events_move.send(MoveDuck {
+                duck: entity,
+                direction,
+            });
+        }
+    }
+}
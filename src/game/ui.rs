@@ -3,8 +3,17 @@ use bevy::window::PrimaryWindow;
 use self::level::Levels;
 
 use super::{
+    accessibility::{Accessibility, LARGE_TEXT_SCALE},
+    autoplay::Autoplay,
+    controls::{Action, Actions, Bindings},
     cursor::click_detection,
-    level::{BreadCount, CurrentLevelIndex, TotalBreadCount},
+    goal::{goals_progress, BoardState},
+    level::{
+        CurrentLevelIndex, Level, LevelConfig, LevelStack, MoveRecordStack, RestartLevel,
+        TotalBreadCount, UndoLevel,
+    },
+    solver::Stuck,
+    theme::{BackgroundRole, BorderRole, TextRole, Theme, ThemeRole},
     *,
 };
 pub struct Plugin;
@@ -17,7 +26,10 @@ impl bevy::app::Plugin for Plugin {
                 show_title_and_name,
                 show_level_title,
                 show_hints,
-                show_stuffed_ducks_count,
+                show_goals,
+                show_stuck_banner,
+                show_autoplay_status,
+                show_touch_buttons,
             ),
         )
         .add_event::<Won>()
@@ -30,21 +42,60 @@ impl bevy::app::Plugin for Plugin {
                 // It fixes the bug when click the next level button and a duck simsimultaneously
                 // If not doing so, click_detection will try to insert Player bundle to an invalid entity, causes the game to crash
                 next_level_button_interaction.after(click_detection),
-                update_stuffed_ducks_count,
+                next_level_button_confirm.after(click_detection),
+                update_goals
+                    .run_if(resource_changed::<Level>.or_else(resource_changed::<MoveRecordStack>)),
+                update_stuck_banner.run_if(resource_changed::<Stuck>),
+                update_autoplay_status
+                    .run_if(resource_changed::<Autoplay>.or_else(resource_changed::<Bindings>)),
+                update_hints
+                    .run_if(resource_changed::<Bindings>.or_else(resource_changed::<Theme>)),
+                update_stuck_banner_text
+                    .run_if(resource_changed::<Bindings>.or_else(resource_changed::<Theme>)),
+                reveal_touch_buttons,
+                touch_button_interaction,
+                scale_ui,
             ),
         );
     }
 }
 
-fn show_title_and_name(mut commands: Commands, asset_server: Res<AssetServer>) {
+// The layout is made for this window size, the ui grows and shrinks with the window
+const REFERENCE_WIDTH: f32 = 1280.0;
+const REFERENCE_HEIGHT: f32 = 720.0;
+
+fn scale_ui(
+    window_query: Query<Ref<Window>, With<PrimaryWindow>>,
+    accessibility: Res<Accessibility>,
+    mut ui_scale: ResMut<UiScale>,
+) {
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+    if !window.is_changed() && !accessibility.is_changed() {
+        return;
+    }
+    let mut scale = (window.width() / REFERENCE_WIDTH)
+        .min(window.height() / REFERENCE_HEIGHT)
+        .clamp(0.5, 2.0);
+    if accessibility.large_text {
+        scale *= LARGE_TEXT_SCALE;
+    }
+    // Window changes with the cursor too, only touch UiScale when the size does
+    if ui_scale.0 != scale {
+        ui_scale.0 = scale;
+    }
+}
+
+fn show_title_and_name(mut commands: Commands, asset_server: Res<AssetServer>, theme: Res<Theme>) {
     // game title
-    commands.spawn(
+    commands.spawn((
         TextBundle::from_section(
             "QUACK!!! on ICE",
             TextStyle {
                 font: asset_server.load("fonts/NotJamChunky8.ttf"),
                 font_size: 30.0,
-                color: MY_ORANGE,
+                color: theme.accent,
             },
         )
         .with_text_justify(JustifyText::Right)
@@ -54,15 +105,17 @@ fn show_title_and_name(mut commands: Commands, asset_server: Res<AssetServer>) {
             right: Val::Px(10.0),
             ..default()
         }),
-    );
+        TextRole(ThemeRole::Accent),
+    ));
 
     // author name
-    commands.spawn(
+    commands.spawn((
         TextBundle::from_section(
             "a game by Minda Chen",
             TextStyle {
                 font: asset_server.load("fonts/NotJamChunky8.ttf"),
                 font_size: 20.0,
+                color: theme.text,
                 ..default()
             },
         )
@@ -73,60 +126,200 @@ fn show_title_and_name(mut commands: Commands, asset_server: Res<AssetServer>) {
             right: Val::Px(10.0),
             ..default()
         }),
-    );
+        TextRole(ThemeRole::Text),
+    ));
 }
 
+// Progress of the level goals, "eaten bread/total bread" by default
 #[derive(Component)]
-struct StuffedDucksCount;
+struct GoalsText;
 
-fn show_stuffed_ducks_count(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    bread_count: Res<BreadCount>,
+fn show_goals(mut commands: Commands, asset_server: Res<AssetServer>, theme: Res<Theme>) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.0),
+                width: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load("fonts/NotJamChunky8.ttf"),
+                        font_size: 30.0,
+                        color: theme.text,
+                        ..default()
+                    },
+                )
+                .with_text_justify(JustifyText::Center),
+                GoalsText,
+                TextRole(ThemeRole::Text),
+            ));
+        });
+}
+
+fn update_goals(
+    level: Res<Level>,
+    config: Res<LevelConfig>,
+    level_stack: Res<LevelStack>,
     total_bread_count: Res<TotalBreadCount>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut goals_text: Query<&mut Text, With<GoalsText>>,
 ) {
-    let window = window_query.get_single().unwrap();
-    commands.spawn((
-        TextBundle::from_section(
-            format!(
-                "{}/{}",
-                total_bread_count.0 - bread_count.0,
-                total_bread_count.0
-            ),
-            TextStyle {
-                font: asset_server.load("fonts/NotJamChunky8.ttf"),
-                font_size: 30.0,
+    let board = BoardState {
+        level: &level.0,
+        config: &config,
+        moves: level_stack.0.size().saturating_sub(1),
+        total_bread: total_bread_count.0,
+    };
+    for mut text in goals_text.iter_mut() {
+        text.sections[0].value = goals_progress(&board);
+    }
+}
+
+// Shown when the level can't be won from the current board anymore
+#[derive(Component)]
+struct StuckBanner;
+
+// "Stuck? Press Z to undo or R to restart", generated from the current bindings
+#[derive(Component)]
+struct StuckBannerText;
+
+fn show_stuck_banner(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(50.0),
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            StuckBanner,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::default().with_text_justify(JustifyText::Center),
+                StuckBannerText,
+            ));
+        });
+}
+
+fn update_stuck_banner_text(
+    asset_server: Res<AssetServer>,
+    bindings: Res<Bindings>,
+    mut banner_text_query: Query<&mut Text, With<StuckBannerText>>,
+    theme: Res<Theme>,
+) {
+    let text_style_important = TextStyle {
+        font: asset_server.load("fonts/NotJamChunky8.ttf"),
+        font_size: 20.0,
+        color: theme.accent,
+    };
+    let text_style_normal = TextStyle {
+        font: asset_server.load("fonts/NotJamChunky8.ttf"),
+        font_size: 20.0,
+        color: theme.text,
+        ..default()
+    };
+    let sections = vec![
+        TextSection::new("Stuck? Press ", text_style_normal.clone()),
+        TextSection::new(
+            format!("{} ", bindings.key_name(Action::Undo)),
+            text_style_important.clone(),
+        ),
+        TextSection::new("to undo or ", text_style_normal.clone()),
+        TextSection::new(
+            format!("{} ", bindings.key_name(Action::Restart)),
+            text_style_important,
+        ),
+        TextSection::new("to restart", text_style_normal),
+    ];
+    for mut text in banner_text_query.iter_mut() {
+        text.sections = sections.clone();
+    }
+}
+
+fn update_stuck_banner(
+    stuck: Res<Stuck>,
+    mut banner_query: Query<&mut Visibility, With<StuckBanner>>,
+) {
+    for mut visibility in banner_query.iter_mut() {
+        *visibility = if stuck.0 {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+// Which move the autoplay is on and how to control it
+#[derive(Component)]
+struct AutoplayStatus;
+
+fn show_autoplay_status(mut commands: Commands, asset_server: Res<AssetServer>, theme: Res<Theme>) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(50.0),
+                width: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
                 ..default()
             },
-        )
-        .with_text_justify(JustifyText::Center)
-        .with_style(Style {
-            position_type: PositionType::Absolute,
-            top: Val::Px(10.0),
-            right: Val::Px(window.width() / 2.0 - 45.0),
             ..default()
-        }),
-        StuffedDucksCount,
-    ));
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load("fonts/NotJamChunky8.ttf"),
+                        font_size: 20.0,
+                        color: theme.accent,
+                    },
+                )
+                .with_text_justify(JustifyText::Center),
+                AutoplayStatus,
+                TextRole(ThemeRole::Accent),
+            ));
+        });
 }
 
-fn update_stuffed_ducks_count(
-    bread_count: Res<BreadCount>,
-    total_bread_count: Res<TotalBreadCount>,
-    mut stuffed_ducks_count: Query<(&mut Text, &mut Style), With<StuffedDucksCount>>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
+fn update_autoplay_status(
+    autoplay: Res<Autoplay>,
+    bindings: Res<Bindings>,
+    mut status_query: Query<&mut Text, With<AutoplayStatus>>,
 ) {
-    let window = window_query.get_single().unwrap();
-    for (mut text, mut style) in stuffed_ducks_count.iter_mut() {
-        text.sections[0].value = format!(
-            "{}/{}",
-            total_bread_count.0 - bread_count.0,
-            total_bread_count.0
-        );
-        // TODO: Find a better way to do UI alighment after resizing the window
-        // window resize event should also change the "RESIZE"
-        style.right = Val::Px(window.width() / 2.0 - 45.0);
+    let status = if autoplay.is_active() {
+        format!(
+            "Autoplay {}/{} x{}{}\n{} pause  {} step  {} {} speed  {} stop",
+            autoplay.next,
+            autoplay.steps.len(),
+            autoplay.speed,
+            if autoplay.paused { " paused" } else { "" },
+            bindings.key_name(Action::PauseAutoplay),
+            bindings.key_name(Action::StepAutoplay),
+            bindings.key_name(Action::SlowerAutoplay),
+            bindings.key_name(Action::FasterAutoplay),
+            bindings.key_name(Action::Autoplay),
+        )
+    } else {
+        String::new()
+    };
+    for mut text in status_query.iter_mut() {
+        if text.sections[0].value != status {
+            text.sections[0].value = status.clone();
+        }
     }
 }
 
@@ -137,6 +330,7 @@ fn show_level_title(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     level_index: Res<CurrentLevelIndex>,
+    theme: Res<Theme>,
 ) {
     commands.spawn((
         TextBundle::from_section(
@@ -144,6 +338,7 @@ fn show_level_title(
             TextStyle {
                 font: asset_server.load("fonts/NotJamChunky8.ttf"),
                 font_size: 30.0,
+                color: theme.text,
                 ..default()
             },
         )
@@ -155,45 +350,109 @@ fn show_level_title(
             ..default()
         }),
         LevelTitle,
+        TextRole(ThemeRole::Text),
     ));
 }
 
-// HINTS:
+// HINTS, generated from the current bindings:
 // Click to choose the duck
+// Tab 1-9 to choose without a mouse
 // WASD to move
 // R to reset
 // Z to undo
+// H to get a hint
+// P to watch the solution
+// F5 F9 to save, play the replay
+// [ ] to skip levels
+// F1 to change the controls
 // One duck, one bread
-fn show_hints(mut commands: Commands, asset_server: Res<AssetServer>) {
+#[derive(Component)]
+struct HintsText;
+
+fn show_hints(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::default()
+            .with_text_justify(JustifyText::Right)
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.0),
+                right: Val::Px(10.0),
+                ..default()
+            }),
+        HintsText,
+    ));
+}
+
+fn update_hints(
+    asset_server: Res<AssetServer>,
+    bindings: Res<Bindings>,
+    mut hints_query: Query<&mut Text, With<HintsText>>,
+    theme: Res<Theme>,
+) {
     let text_style_important = TextStyle {
         font: asset_server.load("fonts/NotJamChunky8.ttf"),
         font_size: 20.0,
-        color: MY_ORANGE,
+        color: theme.accent,
     };
     let text_style_normal = TextStyle {
         font: asset_server.load("fonts/NotJamChunky8.ttf"),
         font_size: 20.0,
+        color: theme.text,
         ..default()
     };
-    commands.spawn((TextBundle::from_sections([
-        TextSection::new("Click ", text_style_important.clone()),
-        TextSection::new("to choose the duck\n", text_style_normal.clone()),
-        TextSection::new("WASD ", text_style_important.clone()),
-        TextSection::new("to move\n", text_style_normal.clone()),
-        TextSection::new("R ", text_style_important.clone()),
-        TextSection::new("to reset\n", text_style_normal.clone()),
-        TextSection::new("Z ", text_style_important.clone()),
-        TextSection::new("to undo\n", text_style_normal.clone()),
-        TextSection::new("[ ] ", text_style_important.clone()),
-        TextSection::new("to skip levels\n\n", text_style_normal.clone()),
-    ])
-    .with_text_justify(JustifyText::Right)
-    .with_style(Style {
-        position_type: PositionType::Absolute,
-        top: Val::Px(10.0),
-        right: Val::Px(10.0),
-        ..default()
-    }),));
+    let keys = |actions: &[Action]| {
+        let names: Vec<String> = actions
+            .iter()
+            .map(|&action| bindings.key_name(action))
+            .collect();
+        // "WASD" reads better than "W A S D"
+        if names.iter().all(|name| name.chars().count() == 1) {
+            names.concat()
+        } else {
+            names.join(" ")
+        }
+    };
+    let hints = [
+        ("Click".to_string(), "to choose the duck\n"),
+        (
+            format!("{} 1-9", keys(&[Action::NextDuck])),
+            "to choose without a mouse\n",
+        ),
+        (
+            keys(&[
+                Action::MoveUp,
+                Action::MoveLeft,
+                Action::MoveDown,
+                Action::MoveRight,
+            ]),
+            "to move\n",
+        ),
+        (keys(&[Action::Restart]), "to reset\n"),
+        (keys(&[Action::Undo]), "to undo\n"),
+        (keys(&[Action::Hint]), "to get a hint\n"),
+        (keys(&[Action::Autoplay]), "to watch the solution\n"),
+        (
+            keys(&[Action::SaveReplay, Action::PlayReplay]),
+            "to save, play the replay\n",
+        ),
+        (
+            keys(&[Action::PreviousLevel, Action::NextLevel]),
+            "to skip levels\n",
+        ),
+        (keys(&[Action::Controls]), "to change the controls\n\n"),
+    ];
+    let sections: Vec<TextSection> = hints
+        .into_iter()
+        .flat_map(|(keys, text)| {
+            [
+                TextSection::new(format!("{} ", keys), text_style_important.clone()),
+                TextSection::new(text, text_style_normal.clone()),
+            ]
+        })
+        .collect();
+    for mut text in hints_query.iter_mut() {
+        text.sections = sections.clone();
+    }
 }
 
 fn update_level_title(
@@ -214,10 +473,122 @@ fn update_level_title(
     }
 }
 
-const NORMAL_BUTTON: Color = MY_ORANGE;
-const HOVERED_BUTTON: Color =
-    Color::srgb(222.0 / 255.0 + 0.1, 112.0 / 255.0 + 0.1, 40.0 / 255.0 + 0.1);
-const PRESSED_BUTTON: Color = Color::srgb(0.75, 0.75, 0.75);
+// On-screen undo and restart for players without a keyboard, shown after the first touch
+#[derive(Component, Clone, Copy)]
+enum TouchButton {
+    Undo,
+    Restart,
+}
+
+#[derive(Component)]
+struct TouchButtons;
+
+fn show_touch_buttons(mut commands: Commands, asset_server: Res<AssetServer>, theme: Res<Theme>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(50.0),
+                    left: Val::Px(10.0),
+                    column_gap: Val::Px(10.0),
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            TouchButtons,
+        ))
+        .with_children(|parent| {
+            for (button, label) in [
+                (TouchButton::Undo, "Undo"),
+                (TouchButton::Restart, "Restart"),
+            ] {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(120.0),
+                                height: Val::Px(60.0),
+                                border: UiRect::all(Val::Px(5.0)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            border_color: BorderColor(theme.secondary),
+                            background_color: theme.accent.into(),
+                            ..default()
+                        },
+                        button,
+                        BackgroundRole(ThemeRole::Accent),
+                        BorderRole(ThemeRole::Secondary),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            TextBundle::from_section(
+                                label,
+                                TextStyle {
+                                    font: asset_server.load("fonts/NotJamChunky8.ttf"),
+                                    font_size: 16.0,
+                                    color: theme.text,
+                                },
+                            ),
+                            TextRole(ThemeRole::Text),
+                        ));
+                    });
+            }
+        });
+}
+
+fn reveal_touch_buttons(
+    touches: Res<Touches>,
+    mut buttons_query: Query<&mut Visibility, With<TouchButtons>>,
+) {
+    if touches.any_just_pressed() {
+        for mut visibility in buttons_query.iter_mut() {
+            *visibility = Visibility::Inherited;
+        }
+    }
+}
+
+fn touch_button_interaction(
+    mut interaction_query: Query<
+        (
+            &Interaction,
+            &TouchButton,
+            &mut BackgroundRole,
+            &mut BorderRole,
+        ),
+        Changed<Interaction>,
+    >,
+    mut events_restart: EventWriter<RestartLevel>,
+    mut events_undo: EventWriter<UndoLevel>,
+) {
+    for (interaction, button, mut background_role, mut border_role) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                *background_role = BackgroundRole(ThemeRole::Pressed);
+                *border_role = BorderRole(ThemeRole::Text);
+                match button {
+                    TouchButton::Undo => {
+                        events_undo.send(UndoLevel);
+                    }
+                    TouchButton::Restart => {
+                        events_restart.send(RestartLevel);
+                    }
+                }
+            }
+            Interaction::Hovered => {
+                *background_role = BackgroundRole(ThemeRole::Hovered);
+                *border_role = BorderRole(ThemeRole::Text);
+            }
+            Interaction::None => {
+                *background_role = BackgroundRole(ThemeRole::Accent);
+                *border_role = BorderRole(ThemeRole::Secondary);
+            }
+        }
+    }
+}
 
 #[derive(Event, Default)]
 pub struct Won;
@@ -232,112 +603,143 @@ fn won(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut events: EventReader<Won>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
     level_index: Res<CurrentLevelIndex>,
     levels: Res<Levels>,
+    theme: Res<Theme>,
 ) {
     for _ in events.read() {
-        let window = window_query.get_single().unwrap();
-        commands.spawn((
-            TextBundle::from_section(
-                "Yummy!",
-                TextStyle {
-                    font: asset_server.load("fonts/NotJamChunky8.ttf"),
-                    font_size: 40.0,
-                    color: MY_ORANGE,
-                },
-            )
-            .with_text_justify(JustifyText::Center)
-            .with_style(Style {
-                position_type: PositionType::Absolute,
-                top: Val::Px(40.0),
-                right: Val::Px(window.width() / 2.0 - 140.0),
-                ..default()
-            }),
-            MutUI,
-        ));
-
         let mut button_text = "Next Level";
         if level_index.0 == levels.levels.len() {
             button_text = "The End?";
         }
 
-        // Show next level button
+        // "Yummy!" on the top, next level button in the middle
         commands
             .spawn((NodeBundle {
                 style: Style {
+                    position_type: PositionType::Absolute,
                     width: Val::Percent(100.0),
                     height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
                     align_items: AlignItems::Center,
-                    justify_content: JustifyContent::Center,
                     ..default()
                 },
                 ..default()
             },))
             .insert(MutUI)
             .with_children(|parent| {
+                parent.spawn((
+                    TextBundle::from_section(
+                        "Yummy!",
+                        TextStyle {
+                            font: asset_server.load("fonts/NotJamChunky8.ttf"),
+                            font_size: 40.0,
+                            color: theme.accent,
+                        },
+                    )
+                    .with_text_justify(JustifyText::Center)
+                    .with_style(Style {
+                        margin: UiRect::top(Val::Px(40.0)),
+                        ..default()
+                    }),
+                    MutUI,
+                    TextRole(ThemeRole::Accent),
+                ));
                 parent
-                    .spawn(ButtonBundle {
+                    .spawn(NodeBundle {
                         style: Style {
-                            width: Val::Px(200.0),
-                            height: Val::Px(80.0),
-                            border: UiRect::all(Val::Px(5.0)),
-                            // horizontally center child text
-                            justify_content: JustifyContent::Center,
-                            // vertically center child text
+                            flex_grow: 1.0,
                             align_items: AlignItems::Center,
                             ..default()
                         },
-                        border_color: BorderColor(Color::BLACK),
-                        background_color: NORMAL_BUTTON.into(),
                         ..default()
                     })
                     .insert(MutUI)
-                    .insert(NextLevelButton)
                     .with_children(|parent| {
                         parent
-                            .spawn(TextBundle::from_section(
-                                button_text,
-                                TextStyle {
-                                    font: asset_server.load("fonts/NotJamChunky8.ttf"),
-                                    font_size: 20.0,
-                                    color: Color::WHITE,
+                            .spawn(ButtonBundle {
+                                style: Style {
+                                    width: Val::Px(200.0),
+                                    height: Val::Px(80.0),
+                                    border: UiRect::all(Val::Px(5.0)),
+                                    // horizontally center child text
+                                    justify_content: JustifyContent::Center,
+                                    // vertically center child text
+                                    align_items: AlignItems::Center,
+                                    ..default()
                                 },
+                                border_color: BorderColor(theme.secondary),
+                                background_color: theme.accent.into(),
+                                ..default()
+                            })
+                            .insert(MutUI)
+                            .insert(NextLevelButton)
+                            .insert((
+                                BackgroundRole(ThemeRole::Accent),
+                                BorderRole(ThemeRole::Secondary),
                             ))
-                            .insert(MutUI);
+                            .with_children(|parent| {
+                                parent
+                                    .spawn(TextBundle::from_section(
+                                        button_text,
+                                        TextStyle {
+                                            font: asset_server.load("fonts/NotJamChunky8.ttf"),
+                                            font_size: 20.0,
+                                            color: theme.text,
+                                        },
+                                    ))
+                                    .insert((MutUI, TextRole(ThemeRole::Text)));
+                            });
                     });
             });
     }
 }
 
+// The confirm action presses the next level button without a mouse
+fn next_level_button_confirm(
+    mut button_query: Query<(&mut BackgroundRole, &mut BorderRole), With<NextLevelButton>>,
+    actions: Res<Actions>,
+    mut level_index: ResMut<CurrentLevelIndex>,
+) {
+    if !actions.just_pressed(Action::Confirm) {
+        return;
+    }
+    // Only one next level button is shown at a time
+    if let Some((mut background_role, mut border_role)) = button_query.iter_mut().next() {
+        *background_role = BackgroundRole(ThemeRole::Pressed);
+        *border_role = BorderRole(ThemeRole::Text);
+        level_index.0 += 1;
+    }
+}
+
 fn next_level_button_interaction(
     mut interaction_query: Query<
-        (&Interaction, &mut BackgroundColor, &mut BorderColor),
+        (&Interaction, &mut BackgroundRole, &mut BorderRole),
         (Changed<Interaction>, (With<Button>, With<NextLevelButton>)),
     >,
     mut level_index: ResMut<CurrentLevelIndex>,
     levels: Res<level::Levels>,
 ) {
     // Handle invalid level index
-    if level::load_level(level_index.0, levels).is_err() {
+    if level::load_level(level_index.0, &levels).is_err() {
         info!("Invalid level index");
         level_index.0 -= 1;
         return;
     }
-    for (interaction, mut color, mut border_color) in &mut interaction_query {
+    for (interaction, mut background_role, mut border_role) in &mut interaction_query {
         match *interaction {
             Interaction::Pressed => {
-                *color = PRESSED_BUTTON.into();
-                border_color.0 = Color::WHITE;
+                *background_role = BackgroundRole(ThemeRole::Pressed);
+                *border_role = BorderRole(ThemeRole::Text);
                 level_index.0 += 1;
             }
             Interaction::Hovered => {
-                *color = HOVERED_BUTTON.into();
-                border_color.0 = Color::WHITE;
+                *background_role = BackgroundRole(ThemeRole::Hovered);
+                *border_role = BorderRole(ThemeRole::Text);
             }
             Interaction::None => {
-                *color = NORMAL_BUTTON.into();
-                border_color.0 = MY_BROWN;
+                *background_role = BackgroundRole(ThemeRole::Accent);
+                *border_role = BorderRole(ThemeRole::Secondary);
             }
         }
     }
@@ -1,6 +1,6 @@
 use super::*;
 
-#[derive(Default, Clone, Copy, PartialEq, Eq)]
+#[derive(Default, Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum Direction {
     Up,
     Down,
@@ -10,6 +10,62 @@ pub enum Direction {
     None,
 }
 
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+
+    // Row and column offsets of one step, Up: row--, Down: row++, Left: col--, Right: col++
+    pub fn delta(self) -> (isize, isize) {
+        match self {
+            Direction::Up => (-1, 0),
+            Direction::Down => (1, 0),
+            Direction::Left => (0, -1),
+            Direction::Right => (0, 1),
+            Direction::None => (0, 0),
+        }
+    }
+
+    // Rotation of a sprite drawn facing down
+    pub fn rotation(self) -> Quat {
+        match self {
+            Direction::Up => Quat::from_rotation_z(std::f32::consts::PI),
+            Direction::Left => Quat::from_rotation_z(-std::f32::consts::FRAC_PI_2),
+            Direction::Right => Quat::from_rotation_z(std::f32::consts::FRAC_PI_2),
+            Direction::Down | Direction::None => Quat::IDENTITY,
+        }
+    }
+}
+
+// Pressed on any gamepad
+pub fn gamepad_just_pressed(
+    input: &ButtonInput<GamepadButton>,
+    button_type: GamepadButtonType,
+) -> bool {
+    input
+        .get_just_pressed()
+        .any(|button| button.button_type == button_type)
+}
+
+// Step one tile from the logic position, None if it leaves the level
+pub fn step(
+    level: &[Vec<char>],
+    logic_position: (usize, usize),
+    direction: Direction,
+) -> Option<(usize, usize)> {
+    if direction == Direction::None {
+        return None;
+    }
+    let (delta_row, delta_col) = direction.delta();
+    let row = logic_position.0.checked_add_signed(delta_row)?;
+    let col = logic_position.1.checked_add_signed(delta_col)?;
+    level.get(row)?.get(col)?;
+    Some((row, col))
+}
+
 // impl Direction {
 //     pub fn to_vector(self) -> Vec2 {
 //         match self {
@@ -22,15 +78,32 @@ pub enum Direction {
 //     }
 // }
 
-// Convert logic position in level to translation
+// Convert logic position in level to translation, the top left tile is at the origin
+// The camera follows the board, see camera.rs
 pub fn logic_position_to_translation(logic_position: (usize, usize)) -> Vec3 {
     Vec3::new(
-        logic_position.1 as f32 * SPRITE_SIZE + 720.0 / 4.0,
-        logic_position.0 as f32 * (-SPRITE_SIZE) + 720.0 * (3.0 / 4.0),
+        logic_position.1 as f32 * SPRITE_SIZE,
+        logic_position.0 as f32 * (-SPRITE_SIZE),
         0.0,
     )
 }
 
+// The tile under a translation, None off the top or left of the board
+pub fn translation_to_logic_position(translation: Vec3) -> Option<(usize, usize)> {
+    let row = (-translation.y / SPRITE_SIZE).round();
+    let col = (translation.x / SPRITE_SIZE).round();
+    if row < 0.0 || col < 0.0 {
+        return None;
+    }
+    Some((row as usize, col as usize))
+}
+
+// A board from its rows, for the tests
+#[cfg(test)]
+pub fn board(rows: &[&str]) -> Vec<Vec<char>> {
+    rows.iter().map(|row| row.chars().collect()).collect()
+}
+
 // Define a generic Stack struct
 pub struct Stack<T> {
     items: Vec<T>,
@@ -61,6 +134,11 @@ impl<T> Stack<T> {
         self.items.last()
     }
 
+    // From the bottom to the top
+    pub fn items(&self) -> &[T] {
+        &self.items
+    }
+
     pub fn clear(&mut self) {
         while !self.is_empty() {
             self.pop();
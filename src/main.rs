@@ -9,8 +9,27 @@ use bevy_tweening::TweeningPlugin;
 mod game;
 
 fn main() {
+    // Check a replay without opening the game
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(path) = std::env::args()
+        .skip_while(|arg| arg != "--verify-replay")
+        .nth(1)
+    {
+        match game::verify_replay_file(&path) {
+            Ok(true) => println!("{}: the replay wins the level", path),
+            Ok(false) => {
+                println!("{}: the replay doesn't win the level", path);
+                std::process::exit(1);
+            }
+            Err(err) => {
+                eprintln!("{}: {}", path, err);
+                std::process::exit(2);
+            }
+        }
+        return;
+    }
+
     App::new()
-        .insert_resource(ClearColor(game::DARK_MODE_BG_COLOR))
         .add_plugins(
             DefaultPlugins
                 .set(WindowPlugin {